@@ -0,0 +1,422 @@
+use std::env;
+use std::ffi::OsString;
+use std::mem;
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
+use uuid::Uuid;
+
+use crate::db::{FieldValue, Sort, TaskQuery};
+
+pub const USAGE: &str = "usage: rustodo [--db <path> | --ephemeral] [--trash-days <days>] [--backups <n>]
+               [--archive-days <days>]
+               [report | list [<filter>...] | backup <file> | restore <file> | doctor [--fix]]
+filters: --done --open --due-before <date> --due-after <date> --completed-since <date>
+         --tag <tag> --project <name> --text <text> --uuid <uuid> --field <name>=<value>
+         --sort <manual|priority|due> --sort-field <name> --limit <n> --offset <n>";
+
+const TRASH_DAYS: u32 = 30;
+const BACKUPS: usize = 5;
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Run,
+    Report,
+    List {
+        query: TaskQuery,
+        project: Option<String>,
+    },
+    Backup(PathBuf),
+    Restore(PathBuf),
+    Doctor {
+        fix: bool,
+    },
+}
+
+pub struct Config {
+    pub db: PathBuf,
+    pub ephemeral: bool,
+    pub trash_days: u32,
+    pub archive_days: Option<u32>,
+    pub backups: usize,
+    pub command: Command,
+}
+
+impl Config {
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(env::args().skip(1), |key| env::var_os(key))
+    }
+
+    fn parse<I, F>(args: I, var: F) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = String>,
+        F: Fn(&str) -> Option<OsString>,
+    {
+        let mut db = None;
+        let mut ephemeral = false;
+        let mut trash_days = None;
+        let mut archive_days = None;
+        let mut backups = None;
+        let mut command = Command::Run;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--db" => match args.next() {
+                    Some(path) => db = Some(PathBuf::from(path)),
+                    None => return Err(String::from("--db requires a path")),
+                },
+                "--ephemeral" => ephemeral = true,
+                "--trash-days" => match args.next() {
+                    Some(days) => trash_days = Some(Self::parse_days("--trash-days", &days)?),
+                    None => return Err(String::from("--trash-days requires a number of days")),
+                },
+                "--archive-days" => match args.next() {
+                    Some(days) => archive_days = Some(Self::parse_days("--archive-days", &days)?),
+                    None => return Err(String::from("--archive-days requires a number of days")),
+                },
+                "--backups" => match args.next() {
+                    Some(count) => backups = Some(Self::parse_backups("--backups", &count)?),
+                    None => return Err(String::from("--backups requires a number of backups")),
+                },
+                "report" if command == Command::Run => command = Command::Report,
+                "backup" | "restore" if command == Command::Run => {
+                    let path = match args.next() {
+                        Some(path) => PathBuf::from(path),
+                        None => return Err(format!("{} requires a file", arg)),
+                    };
+                    command = match arg.as_str() {
+                        "backup" => Command::Backup(path),
+                        _ => Command::Restore(path),
+                    }
+                }
+                "doctor" if command == Command::Run => command = Command::Doctor { fix: false },
+                "--fix" if command == (Command::Doctor { fix: false }) => command = Command::Doctor { fix: true },
+                "list" if command == Command::Run => {
+                    command = Command::List {
+                        query: TaskQuery::new(),
+                        project: None,
+                    }
+                }
+                _ => match &mut command {
+                    Command::List { query, project } => {
+                        *query = Self::parse_filter(&arg, &mut args, mem::take(query), project)?
+                    }
+                    _ => return Err(format!("unknown argument: {}", arg)),
+                },
+            }
+        }
+
+        let db = match db {
+            Some(db) => db,
+            None => match var("RUSTODO_DB").filter(|path| !path.is_empty()) {
+                Some(path) => PathBuf::from(path),
+                None => match Self::data_dir(&var) {
+                    Some(dir) => dir.join("rustodo").join("tasks.db"),
+                    None if ephemeral => PathBuf::new(),
+                    None => {
+                        return Err(String::from(
+                            "cannot find a data directory; set RUSTODO_DB or use --db",
+                        ))
+                    }
+                },
+            },
+        };
+
+        let trash_days = match trash_days {
+            Some(days) => days,
+            None => match var("RUSTODO_TRASH_DAYS") {
+                Some(days) => Self::parse_days("RUSTODO_TRASH_DAYS", &days.to_string_lossy())?,
+                None => TRASH_DAYS,
+            },
+        };
+
+        let archive_days = match archive_days {
+            Some(days) => Some(days),
+            None => match var("RUSTODO_ARCHIVE_DAYS") {
+                Some(days) => Some(Self::parse_days("RUSTODO_ARCHIVE_DAYS", &days.to_string_lossy())?),
+                None => None,
+            },
+        };
+
+        let backups = match backups {
+            Some(count) => count,
+            None => match var("RUSTODO_BACKUPS") {
+                Some(count) => Self::parse_backups("RUSTODO_BACKUPS", &count.to_string_lossy())?,
+                None => BACKUPS,
+            },
+        };
+
+        if ephemeral && matches!(command, Command::Backup(_) | Command::Restore(_) | Command::Doctor { .. }) {
+            return Err(String::from("backup, restore and doctor need a database file, not --ephemeral"));
+        }
+
+        Ok(Self {
+            db,
+            ephemeral,
+            trash_days,
+            archive_days,
+            backups,
+            command,
+        })
+    }
+
+    fn parse_filter<I>(
+        arg: &str,
+        args: &mut I,
+        query: TaskQuery,
+        project: &mut Option<String>,
+    ) -> Result<TaskQuery, String>
+    where
+        I: Iterator<Item = String>,
+    {
+        let mut value = || args.next().ok_or(format!("{} requires a value", arg));
+        let date = |value: String| {
+            NaiveDate::parse_from_str(&value, "%Y-%m-%d")
+                .map_err(|_| format!("{} must be a YYYY-MM-DD date, got {:?}", arg, value))
+        };
+        let number = |value: String| {
+            value
+                .parse()
+                .map_err(|_| format!("{} must be a number, got {:?}", arg, value))
+        };
+
+        Ok(match arg {
+            "--done" => query.done(true),
+            "--open" => query.done(false),
+            "--due-before" => query.due_before(date(value()?)?),
+            "--due-after" => query.due_after(date(value()?)?),
+            "--completed-since" => query.completed_since(date(value()?)?),
+            "--tag" => query.tag(value()?.trim_start_matches('#')),
+            "--text" => query.text(&value()?),
+            "--field" => {
+                let value = value()?;
+                let (name, value) = value
+                    .split_once('=')
+                    .filter(|(name, _)| !name.trim().is_empty())
+                    .ok_or(format!("{} must be <name>=<value>, got {:?}", arg, value))?;
+                query.field(name.trim(), FieldValue::parse(value))
+            }
+            "--sort-field" => query.sort_field(&value()?),
+            "--uuid" => {
+                let value = value()?;
+                let uuid = Uuid::parse_str(&value)
+                    .map_err(|_| format!("{} must be a UUID, got {:?}", arg, value))?;
+                query.uuid(uuid)
+            }
+            "--limit" => query.limit(number(value()?)?),
+            "--offset" => query.offset(number(value()?)?),
+            "--project" => {
+                *project = Some(value()?);
+                query
+            }
+            "--sort" => {
+                let name = value()?;
+                let sort = [Sort::Position, Sort::Priority, Sort::Due]
+                    .into_iter()
+                    .find(|sort| sort.name() == name)
+                    .ok_or(format!("unknown sort order: {}", name))?;
+                query.sort(sort)
+            }
+            _ => return Err(format!("unknown argument: {}", arg)),
+        })
+    }
+
+    fn parse_days(name: &str, days: &str) -> Result<u32, String> {
+        days.parse()
+            .map_err(|_| format!("{} must be a number of days, got {:?}", name, days))
+    }
+
+    fn parse_backups(name: &str, count: &str) -> Result<usize, String> {
+        count
+            .parse()
+            .map_err(|_| format!("{} must be a number of backups, got {:?}", name, count))
+    }
+
+    fn data_dir<F>(var: &F) -> Option<PathBuf>
+    where
+        F: Fn(&str) -> Option<OsString>,
+    {
+        let absolute = |path: OsString| {
+            let path = PathBuf::from(path);
+            if path.is_absolute() {
+                Some(path)
+            } else {
+                None
+            }
+        };
+
+        var("XDG_DATA_HOME")
+            .and_then(absolute)
+            .or_else(|| {
+                var("HOME")
+                    .and_then(absolute)
+                    .map(|home| home.join(".local").join("share"))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<OsString> {
+        let vars: Vec<(String, OsString)> = vars
+            .iter()
+            .map(|(key, value)| (key.to_string(), OsString::from(value)))
+            .collect();
+        move |key| {
+            vars.iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, value)| value.clone())
+        }
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_db_from_xdg_data_home() {
+        let var = vars(&[("XDG_DATA_HOME", "/data"), ("HOME", "/home/user")]);
+        let config = Config::parse(args(&[]), var).unwrap();
+        assert_eq!(config.db, PathBuf::from("/data/rustodo/tasks.db"));
+    }
+
+    #[test]
+    fn test_db_from_home() {
+        let var = vars(&[("XDG_DATA_HOME", "relative"), ("HOME", "/home/user")]);
+        let config = Config::parse(args(&[]), var).unwrap();
+        assert_eq!(
+            config.db,
+            PathBuf::from("/home/user/.local/share/rustodo/tasks.db"),
+        );
+    }
+
+    #[test]
+    fn test_db_from_env_and_flag() {
+        let var = vars(&[("RUSTODO_DB", "/env.db"), ("HOME", "/home/user")]);
+        let config = Config::parse(args(&[]), &var).unwrap();
+        assert_eq!(config.db, PathBuf::from("/env.db"));
+
+        let config = Config::parse(args(&["--db", "flag.db"]), &var).unwrap();
+        assert_eq!(config.db, PathBuf::from("flag.db"));
+    }
+
+    #[test]
+    fn test_trash_days() {
+        let var = vars(&[("RUSTODO_DB", "/env.db"), ("RUSTODO_TRASH_DAYS", "7")]);
+        let config = Config::parse(args(&[]), &var).unwrap();
+        assert_eq!(config.trash_days, 7);
+
+        let config = Config::parse(args(&["--trash-days", "0"]), &var).unwrap();
+        assert_eq!(config.trash_days, 0);
+
+        let config = Config::parse(args(&[]), vars(&[("RUSTODO_DB", "/env.db")])).unwrap();
+        assert_eq!(config.trash_days, TRASH_DAYS);
+
+        assert!(Config::parse(args(&["--trash-days", "soon"]), &var).is_err());
+    }
+
+    #[test]
+    fn test_archive_days() {
+        let var = vars(&[("RUSTODO_DB", "/env.db"), ("RUSTODO_ARCHIVE_DAYS", "90")]);
+        assert_eq!(Config::parse(args(&[]), &var).unwrap().archive_days, Some(90));
+        let config = Config::parse(args(&["--archive-days", "0"]), &var).unwrap();
+        assert_eq!(config.archive_days, Some(0));
+
+        let config = Config::parse(args(&[]), vars(&[("RUSTODO_DB", "/env.db")])).unwrap();
+        assert_eq!(config.archive_days, None);
+        assert!(Config::parse(args(&["--archive-days"]), &var).is_err());
+    }
+
+    #[test]
+    fn test_command() {
+        let var = vars(&[("RUSTODO_DB", "/env.db")]);
+        assert_eq!(Config::parse(args(&[]), &var).unwrap().command, Command::Run);
+        let config = Config::parse(args(&["report", "--db", "flag.db"]), &var).unwrap();
+        assert_eq!(config.command, Command::Report);
+        assert_eq!(config.db, PathBuf::from("flag.db"));
+        assert!(Config::parse(args(&["report", "report"]), &var).is_err());
+    }
+
+    #[test]
+    fn test_list() {
+        let var = vars(&[("RUSTODO_DB", "/env.db")]);
+        let args = args(&[
+            "list", "--open", "--tag", "#work", "--due-before", "2025-02-01", "--project", "home",
+            "--sort", "priority", "--limit", "5",
+        ]);
+        let config = Config::parse(args, &var).unwrap();
+        let expected = TaskQuery::new()
+            .done(false)
+            .tag("work")
+            .due_before(NaiveDate::from_ymd_opt(2025, 2, 1).unwrap())
+            .sort(Sort::Priority)
+            .limit(5);
+        assert_eq!(
+            config.command,
+            Command::List {
+                query: expected,
+                project: Some(String::from("home")),
+            },
+        );
+
+        assert!(Config::parse(self::args(&["--open"]), &var).is_err());
+        assert!(Config::parse(self::args(&["list", "--due-after", "soon"]), &var).is_err());
+        assert!(Config::parse(self::args(&["list", "--sort", "random"]), &var).is_err());
+        assert!(Config::parse(self::args(&["list", "--limit"]), &var).is_err());
+        assert!(Config::parse(self::args(&["list", "--uuid", "42"]), &var).is_err());
+        assert!(Config::parse(self::args(&["list", "--field", "points"]), &var).is_err());
+        let args = self::args(&["list", "--field", "points=3", "--sort-field", "customer"]);
+        let config = Config::parse(args, &var).unwrap();
+        let expected = TaskQuery::new().field("points", FieldValue::Number(3.0)).sort_field("customer");
+        assert_eq!(config.command, Command::List { query: expected, project: None });
+        let uuid = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+        let config = Config::parse(self::args(&["list", "--uuid", uuid]), &var).unwrap();
+        let expected = TaskQuery::new().uuid(Uuid::parse_str(uuid).unwrap());
+        assert_eq!(config.command, Command::List { query: expected, project: None });
+    }
+
+    #[test]
+    fn test_backup_and_restore() {
+        let var = vars(&[("RUSTODO_DB", "/env.db"), ("RUSTODO_BACKUPS", "3")]);
+        let config = Config::parse(args(&["backup", "copy.db"]), &var).unwrap();
+        assert_eq!(config.command, Command::Backup(PathBuf::from("copy.db")));
+        assert_eq!(config.backups, 3);
+        let config = Config::parse(args(&["--backups", "0", "restore", "copy.db"]), &var).unwrap();
+        assert_eq!(config.command, Command::Restore(PathBuf::from("copy.db")));
+        assert_eq!(config.backups, 0);
+
+        let config = Config::parse(args(&[]), vars(&[("RUSTODO_DB", "/env.db")])).unwrap();
+        assert_eq!(config.backups, BACKUPS);
+        assert!(Config::parse(args(&["backup"]), &var).is_err());
+        assert!(Config::parse(args(&["--backups", "all"]), &var).is_err());
+        assert!(Config::parse(args(&["--ephemeral", "restore", "copy.db"]), &var).is_err());
+    }
+
+    #[test]
+    fn test_doctor() {
+        let var = vars(&[("RUSTODO_DB", "/env.db")]);
+        let config = Config::parse(args(&["doctor"]), &var).unwrap();
+        assert_eq!(config.command, Command::Doctor { fix: false });
+        let config = Config::parse(args(&["doctor", "--fix"]), &var).unwrap();
+        assert_eq!(config.command, Command::Doctor { fix: true });
+        assert!(Config::parse(args(&["--fix"]), &var).is_err());
+        assert!(Config::parse(args(&["--ephemeral", "doctor"]), &var).is_err());
+    }
+
+    #[test]
+    fn test_ephemeral() {
+        let config = Config::parse(args(&["--ephemeral", "list"]), vars(&[])).unwrap();
+        assert!(config.ephemeral);
+        assert!(!Config::parse(args(&[]), vars(&[("RUSTODO_DB", "/env.db")])).unwrap().ephemeral);
+    }
+
+    #[test]
+    fn test_bad_args() {
+        assert!(Config::parse(args(&["--db"]), vars(&[])).is_err());
+        assert!(Config::parse(args(&["--nope"]), vars(&[])).is_err());
+        assert!(Config::parse(args(&[]), vars(&[])).is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use chrono::Local;
+use chrono::{Datelike, Local, NaiveDate};
 use ratatui::{
     buffer::Buffer,
     crossterm::event::{KeyCode, KeyEvent},
@@ -14,7 +14,7 @@ enum DatePickerStatus {
 }
 
 pub struct DatePicker {
-    date: Option<String>,
+    date: Option<NaiveDate>,
     year: [i16; 4],
     month: [i16; 2],
     day: [i16; 2],
@@ -35,144 +35,141 @@ impl DatePicker {
     }
 
     pub fn handle_key_press_event(&mut self, key_event: KeyEvent) -> bool {
-        match self.status {
-            DatePickerStatus::Editing => {
-                match key_event.code {
-                    KeyCode::F(1) => self.done(),
-                    KeyCode::Char('j') => {
-                        if self.position < 4 {
-                            let mut year = self.year[0] * 1000 + self.year[1] * 100 + self.year[2] * 10 + self.year[3];
-                            year -= 10_i16.pow(3 - self.position as u32);
-                            while year < 0 {
-                                year += 10000;
-                            }
-                            self.year = Self::split_four(year);
-                        } else if self.position < 6 {
-                            let mut month = self.month[0] * 10 + self.month[1];
-                            month -= 1;
-                            if month > 12 {
-                                month = 12;
-                            }
-                            while month < 1 {
-                                month += 12;
-                            }
-                            self.position = 5;
-                            self.month = Self::split_two(month);
-                        } else if self.position < 8 {
-                            let year = self.year[0] * 1000 + self.year[1] * 100 + self.year[2] * 10 + self.year[3];
-                            let leap_year = Self::is_leap_year(year);
-
-                            let mut month = self.month[0] * 10 + self.month[1];
-                            month = month.clamp(1, 12);
-
-                            let mut day = self.day[0] * 10 + self.day[1];
-                            let max_day = Self::get_max_day(month, leap_year);
-
-                            day -=1;
-                            if day > max_day {
-                                day = max_day;
-                            }
-                            if day < 1 {
-                                day = max_day;
-                            }
-                            self.position = 7;
-                            self.day = Self::split_two(day);
+        if let DatePickerStatus::Editing = self.status {
+            match key_event.code {
+                KeyCode::F(1) => self.done(),
+                KeyCode::Char('j') => {
+                    if self.position < 4 {
+                        let mut year = self.year[0] * 1000 + self.year[1] * 100 + self.year[2] * 10 + self.year[3];
+                        year -= 10_i16.pow(3 - self.position as u32);
+                        while year < 0 {
+                            year += 10000;
                         }
-                    }
-                    KeyCode::Char('k') => {
-                        if self.position < 4 {
-                            let mut year = self.year[0] * 1000 + self.year[1] * 100 + self.year[2] * 10 + self.year[3];
-                            year += 10_i16.pow(3 - self.position as u32);
-                            year %= 10000;
-                            self.year = Self::split_four(year);
-                        } else if self.position < 6 {
-                            let mut month = self.month[0] * 10 + self.month[1];
-                            month %= 12;
-                            month += 1;
-                            self.position = 5;
-                            self.month = Self::split_two(month);
-                        } else if self.position < 8 {
-                            let year = self.year[0] * 1000 + self.year[1] * 100 + self.year[2] * 10 + self.year[3];
-                            let leap_year = Self::is_leap_year(year);
+                        self.year = Self::split_four(year);
+                    } else if self.position < 6 {
+                        let mut month = self.month[0] * 10 + self.month[1];
+                        month -= 1;
+                        if month > 12 {
+                            month = 12;
+                        }
+                        while month < 1 {
+                            month += 12;
+                        }
+                        self.position = 5;
+                        self.month = Self::split_two(month);
+                    } else if self.position < 8 {
+                        let year = self.year[0] * 1000 + self.year[1] * 100 + self.year[2] * 10 + self.year[3];
+                        let leap_year = Self::is_leap_year(year);
 
-                            let mut month = self.month[0] * 10 + self.month[1];
-                            month = month.clamp(1, 12);
+                        let mut month = self.month[0] * 10 + self.month[1];
+                        month = month.clamp(1, 12);
 
-                            let mut day = self.day[0] * 10 + self.day[1];
-                            let max_day = Self::get_max_day(month, leap_year);
+                        let mut day = self.day[0] * 10 + self.day[1];
+                        let max_day = Self::get_max_day(month, leap_year);
 
-                            if day >= max_day {
-                                day = 0;
-                            }
-                            day +=1;
-                            self.position = 7;
-                            self.day = Self::split_two(day);
+                        day -=1;
+                        if day > max_day {
+                            day = max_day;
                         }
-                    }
-                    KeyCode::Char('h') => {
-                        if self.position > 0 {
-                            self.position -= 1;
-                        } else {
-                            self.position = 7;
+                        if day < 1 {
+                            day = max_day;
                         }
+                        self.position = 7;
+                        self.day = Self::split_two(day);
                     }
-                    KeyCode::Char('l') => {
-                        if self.position < 7 {
-                            self.position += 1;
-                        } else {
-                            self.position = 0;
+                }
+                KeyCode::Char('k') => {
+                    if self.position < 4 {
+                        let mut year = self.year[0] * 1000 + self.year[1] * 100 + self.year[2] * 10 + self.year[3];
+                        year += 10_i16.pow(3 - self.position as u32);
+                        year %= 10000;
+                        self.year = Self::split_four(year);
+                    } else if self.position < 6 {
+                        let mut month = self.month[0] * 10 + self.month[1];
+                        month %= 12;
+                        month += 1;
+                        self.position = 5;
+                        self.month = Self::split_two(month);
+                    } else if self.position < 8 {
+                        let year = self.year[0] * 1000 + self.year[1] * 100 + self.year[2] * 10 + self.year[3];
+                        let leap_year = Self::is_leap_year(year);
+
+                        let mut month = self.month[0] * 10 + self.month[1];
+                        month = month.clamp(1, 12);
+
+                        let mut day = self.day[0] * 10 + self.day[1];
+                        let max_day = Self::get_max_day(month, leap_year);
+
+                        if day >= max_day {
+                            day = 0;
                         }
+                        day +=1;
+                        self.position = 7;
+                        self.day = Self::split_two(day);
                     }
-                    KeyCode::Char(' ') => {
-                        if self.position < 7 {
-                            self.position += 1;
-                        }
+                }
+                KeyCode::Char('h') => {
+                    if self.position > 0 {
+                        self.position -= 1;
+                    } else {
+                        self.position = 7;
                     }
-                    KeyCode::Backspace => {
-                        if self.position > 0 {
-                            self.position -= 1;
-                        }
+                }
+                KeyCode::Char('l') => {
+                    if self.position < 7 {
+                        self.position += 1;
+                    } else {
+                        self.position = 0;
                     }
-                    KeyCode::Tab => {
-                        if self.position < 4 {
-                            self.position = 4;
-                        } else if self.position < 6 {
-                            self.position = 6;
-                        } else {
-                            self.position = 0;
-                        }
+                }
+                KeyCode::Char(' ') => {
+                    if self.position < 7 {
+                        self.position += 1;
                     }
-                    KeyCode::BackTab => {
+                }
+                KeyCode::Backspace => {
+                    if self.position > 0 {
+                        self.position -= 1;
+                    }
+                }
+                KeyCode::Tab => {
+                    if self.position < 4 {
+                        self.position = 4;
+                    } else if self.position < 6 {
+                        self.position = 6;
+                    } else {
+                        self.position = 0;
+                    }
+                }
+                KeyCode::BackTab => {
+                    if self.position < 4 {
+                        self.position = 6;
+                    } else if self.position < 6 {
+                        self.position = 0;
+                    } else {
+                        self.position = 4;
+                    }
+                }
+                _ => {
+                    let int = Self::code_to_int(key_event.code);
+                    if int >= 0 {
                         if self.position < 4 {
-                            self.position = 6;
+                            self.year[self.position] = int;
                         } else if self.position < 6 {
-                            self.position = 0;
-                        } else {
-                            self.position = 4;
+                            self.month[self.position - 4] = int;
+                        } else if self.position < 8 {
+                            self.day[self.position - 6] = int;
                         }
-                    }
-                    _ => {
-                        let int = Self::code_to_int(key_event.code);
-                        if int >= 0 {
-                            if self.position < 4 {
-                                self.year[self.position] = int;
-                            } else if self.position < 6 {
-                                self.month[self.position - 4] = int;
-                            } else if self.position < 8 {
-                                self.day[self.position - 6] = int;
-                            }
 
-                            if self.position < 7 {
-                                self.position += 1;
-                            } else {
-                                self.position = 0;
-                            }
+                        if self.position < 7 {
+                            self.position += 1;
+                        } else {
+                            self.position = 0;
                         }
                     }
-                };
-                return true;
-            }
-            _ => (),
+                }
+            };
+            return true;
         }
 
         false
@@ -188,15 +185,7 @@ impl DatePicker {
     }
 
     fn is_leap_year(year: i16) -> bool {
-        if year % 400 == 0 {
-            true
-        } else if year % 100 == 0 {
-            false
-        } else if year % 4 == 0 {
-            true
-        } else {
-            false
-        }
+        year % 400 == 0 || (year % 100 != 0 && year % 4 == 0)
     }
 
     fn get_max_day(month: i16, leap_year: bool) -> i16 {
@@ -229,99 +218,17 @@ impl DatePicker {
         }
     }
 
-    pub fn start(&mut self, due: &String) {
-        let mut date = due;
-        let today = format!("{}", Local::now().format("%Y/%m/%d"));
-        if date.len() < 10 {
-            date = &today;
-        }
-        self.set_year(&date[0..=3]);
-        self.set_month(&date[5..=6]);
-        self.set_day(&date[8..=9]);
+    pub fn start(&mut self, due: Option<NaiveDate>) {
+        let date = due.unwrap_or_else(|| Local::now().date_naive());
+        self.year = Self::split_four(date.year() as i16);
+        self.month = Self::split_two(date.month() as i16);
+        self.day = Self::split_two(date.day() as i16);
 
         self.position = 0;
 
         self.status = DatePickerStatus::Editing;
     }
 
-    fn parse_four(string: &str) -> Option<[i16; 4]> {
-        let mut not_digit = false;
-        let res = string
-            .chars()
-            .map(|c| {
-                if let Some(value) = c.to_digit(10) {
-                    value as i16
-                } else {
-                    not_digit = true;
-                    0
-                }
-            })
-            .collect::<Vec<_>>()
-            .try_into();
-
-        if not_digit {
-            None
-        } else if let Ok(array) = res {
-            Some(array)
-        } else {
-            None
-        }
-    }
-
-    fn parse_two(string: &str) -> Option<[i16; 2]> {
-        let mut not_digit = false;
-        let res = string
-            .chars()
-            .map(|c| {
-                if let Some(value) = c.to_digit(10) {
-                    value as i16
-                } else {
-                    not_digit = true;
-                    0
-                }
-            })
-            .collect::<Vec<_>>()
-            .try_into();
-
-        if not_digit {
-            None
-        } else if let Ok(array) = res {
-            Some(array)
-        } else {
-            None
-        }
-    }
-
-    fn set_year(&mut self, string: &str) {
-        self.year = match Self::parse_four(string) {
-            Some(array) => array,
-            None => {
-                let year = format!("{}", Local::now().format("%Y"));
-                Self::parse_four(&year).unwrap()
-            }
-        };
-    }
-
-    fn set_month(&mut self, string: &str) {
-        self.month = match Self::parse_two(string) {
-            Some(array) => array,
-            None => {
-                let month = format!("{}", Local::now().format("%m"));
-                Self::parse_two(&month).unwrap()
-            }
-        };
-    }
-
-    fn set_day(&mut self, string: &str) {
-        self.day = match Self::parse_two(string) {
-            Some(array) => array,
-            None => {
-                let day = format!("{}", Local::now().format("%d"));
-                Self::parse_two(&day).unwrap()
-            }
-        };
-    }
-
     fn done(&mut self) {
         let year = self.year[0] * 1000 + self.year[1] * 100 + self.year[2] * 10 + self.year[3];
         let leap_year = Self::is_leap_year(year);
@@ -333,7 +240,7 @@ impl DatePicker {
         let max_day = Self::get_max_day(month, leap_year);
         day = day.clamp(1, max_day);
 
-        self.date = Some(format!("{year:0>4}/{month:0>2}/{day:0>2}"));
+        self.date = NaiveDate::from_ymd_opt(year.into(), month as u32, day as u32);
         self.hide();
     }
 
@@ -341,7 +248,7 @@ impl DatePicker {
         self.status = DatePickerStatus::Hiding;
     }
 
-    pub fn get_date(&mut self) -> Option<String> {
+    pub fn get_date(&mut self) -> Option<NaiveDate> {
         self.date.take()
     }
 }
@@ -1,228 +1,1302 @@
+use std::cell::Cell;
+use std::cmp;
+use std::collections::HashMap;
 use std::path::Path;
 
-use sqlite::{Connection, State};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use sqlite::{Connection, State, Statement, Value};
+use uuid::Uuid;
 
+use crate::recurrence::Recurrence;
+use crate::store::TaskStore;
+
+mod backup;
+mod doctor;
+mod error;
+mod field;
+pub use field::{Field, FieldValue};
+pub use error::{DbError, Result};
+mod migrations;
+use migrations::{MIGRATIONS, SCHEMA_VERSION};
+mod query;
+pub use query::TaskQuery;
+
+#[derive(Clone)]
 pub struct Task {
     pub id: i64,
+    pub uuid: Uuid,
     pub done: bool,
     pub subject: String,
     pub body: String,
-    pub created: String,
-    pub due: String,
+    pub created: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub due: Option<NaiveDate>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub archived_at: Option<DateTime<Utc>>,
+    pub position: i64,
+    pub tags: Vec<String>,
+    pub fields: Vec<Field>,
+    pub project_id: i64,
+    pub parent_id: Option<i64>,
+    pub blockers: Vec<i64>,
+    pub blocked: bool,
+    pub priority: Priority,
+    pub recurrence: Option<Recurrence>,
+    pub version: i64,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Priority {
+    None,
+    Low,
+    Medium,
+    High,
+}
+
+impl Priority {
+    const ALL: [Priority; 4] = [Priority::None, Priority::Low, Priority::Medium, Priority::High];
+
+    fn from_i64(value: i64) -> Option<Self> {
+        Self::ALL.get(usize::try_from(value).ok()?).copied()
+    }
+
+    pub fn raise(self) -> Self {
+        Self::ALL[cmp::min(self as usize + 1, Self::ALL.len() - 1)]
+    }
+
+    pub fn lower(self) -> Self {
+        Self::ALL[(self as usize).saturating_sub(1)]
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Priority::None => "   ",
+            Priority::Low => "!  ",
+            Priority::Medium => "!! ",
+            Priority::High => "!!!",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Sort {
+    #[default]
+    Position,
+    Priority,
+    Due,
+}
+
+impl Sort {
+    fn order_by(self) -> &'static str {
+        match self {
+            Sort::Position => "position, id",
+            Sort::Priority => "priority DESC, due IS NULL, due, position, id",
+            Sort::Due => "due IS NULL, due, priority DESC, position, id",
+        }
+    }
+
+    fn compare(self, a: &Task, b: &Task) -> cmp::Ordering {
+        let position = || a.position.cmp(&b.position).then(a.id.cmp(&b.id));
+        let priority = || b.priority.cmp(&a.priority);
+        let due = || a.due.is_none().cmp(&b.due.is_none()).then(a.due.cmp(&b.due));
+        match self {
+            Sort::Position => position(),
+            Sort::Priority => priority().then_with(due).then_with(position),
+            Sort::Due => due().then_with(priority).then_with(position),
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Sort::Position => Sort::Priority,
+            Sort::Priority => Sort::Due,
+            Sort::Due => Sort::Position,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Sort::Position => "manual",
+            Sort::Priority => "priority",
+            Sort::Due => "due",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Project {
+    pub id: i64,
+    pub name: String,
+    pub archived: bool,
+}
+
+pub const DEFAULT_PROJECT: i64 = 1;
+
+pub struct SearchHit {
+    pub task: Task,
+    pub subject: String,
+    pub body: String,
+    pub snippet: String,
+}
+
+#[derive(Clone)]
+pub struct TimeEntry {
+    pub id: i64,
+    pub task_id: i64,
+    pub started_at: DateTime<Utc>,
+    pub stopped_at: Option<DateTime<Utc>>,
 }
 
+pub struct TimeReport {
+    pub day: NaiveDate,
+    pub task_id: i64,
+    pub subject: String,
+    pub seconds: i64,
+}
+
+#[derive(Clone)]
+pub struct Revision {
+    pub id: i64,
+    pub task_id: i64,
+    pub subject: String,
+    pub body: String,
+    pub created: DateTime<Utc>,
+}
+
+pub const MATCH_START: char = '\u{2}';
+pub const MATCH_END: char = '\u{3}';
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
 pub struct Db {
     connection: Connection,
+    depth: Cell<usize>,
 }
 
 impl Db {
-    pub fn new() -> Self {
-        #[cfg(not(test))]
-        let file = "sqlite.db";
-        #[cfg(test)]
-        let file = "test.db";
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let connection = sqlite::open(path)?;
+        Self::with_connection(connection)
+    }
+
+    pub fn open_unmigrated<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let connection = sqlite::open(path)?;
+        Self::connect(connection)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self> {
+        let db = Self::connect(connection)?;
+        db.upgrade()?;
+        Ok(db)
+    }
+
+    fn connect(mut connection: Connection) -> Result<Self> {
+        connection.set_busy_timeout(5000)?;
+        Ok(Db {
+            connection,
+            depth: Cell::new(0),
+        })
+    }
+
+    fn upgrade(&self) -> Result<()> {
+        self.connection.execute("PRAGMA journal_mode = WAL;")?;
+        self.migrate()?;
+        self.connection.execute("PRAGMA foreign_keys = ON;")?;
+
+        Ok(())
+    }
+
+    fn transaction<T, F>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce() -> Result<T>,
+    {
+        let depth = self.depth.get();
+        let (begin, commit, rollback) = if depth == 0 {
+            ("BEGIN IMMEDIATE;", "COMMIT;", "ROLLBACK;")
+        } else {
+            ("SAVEPOINT nested;", "RELEASE nested;", "ROLLBACK TO nested; RELEASE nested;")
+        };
+
+        self.connection.execute(begin)?;
+        self.depth.set(depth + 1);
+        let result = f();
+        self.depth.set(depth);
+
+        match result {
+            Ok(value) => match self.connection.execute(commit) {
+                Ok(()) => Ok(value),
+                // a failed COMMIT, e.g. SQLITE_BUSY, leaves the transaction open
+                Err(error) => {
+                    let _ = self.connection.execute(rollback);
+                    Err(error.into())
+                }
+            },
+            Err(error) => {
+                // the original error matters more than a failed rollback
+                let _ = self.connection.execute(rollback);
+                Err(error)
+            }
+        }
+    }
+
+    fn migrate(&self) -> Result<()> {
+        self.transaction(|| self.apply_migrations())
+    }
+
+    fn apply_migrations(&self) -> Result<()> {
+        let version = self.user_version()?;
+        if version > SCHEMA_VERSION {
+            return Err(DbError::SchemaTooNew {
+                version,
+                supported: SCHEMA_VERSION,
+            });
+        }
+
+        for (i, sql) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+            self.connection.execute(sql)?;
+            let sql = format!("PRAGMA user_version = {};", i + 1);
+            self.connection.execute(sql)?;
+        }
+
+        Ok(())
+    }
+
+    fn user_version(&self) -> Result<i64> {
+        let mut stat = self.connection.prepare("PRAGMA user_version;")?;
+        stat.next()?;
+        Ok(stat.read::<i64, _>(0)?)
+    }
+
+    fn read_datetime(
+        stat: &Statement,
+        id: i64,
+        column: &'static str,
+    ) -> Result<Option<DateTime<Utc>>> {
+        match stat.read::<Option<String>, _>(column)? {
+            Some(value) => match NaiveDateTime::parse_from_str(&value, DATETIME_FORMAT) {
+                Ok(datetime) => Ok(Some(datetime.and_utc())),
+                Err(_) => Err(DbError::InvalidValue { id, column, value }),
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn read_date(stat: &Statement, id: i64, column: &'static str) -> Result<Option<NaiveDate>> {
+        match stat.read::<Option<String>, _>(column)? {
+            Some(value) => match NaiveDate::parse_from_str(&value, DATE_FORMAT) {
+                Ok(date) => Ok(Some(date)),
+                Err(_) => Err(DbError::InvalidValue { id, column, value }),
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn read_uuid(stat: &Statement, id: i64) -> Result<Uuid> {
+        let value = stat.read::<Option<String>, _>("uuid")?.unwrap_or_default();
+        Uuid::parse_str(&value).map_err(|_| DbError::InvalidValue {
+            id,
+            column: "uuid",
+            value,
+        })
+    }
+
+    fn read_priority(stat: &Statement, id: i64) -> Result<Priority> {
+        let value = stat.read::<i64, _>("priority")?;
+        Priority::from_i64(value).ok_or(DbError::InvalidValue {
+            id,
+            column: "priority",
+            value: value.to_string(),
+        })
+    }
+
+    fn read_recurrence(stat: &Statement, id: i64) -> Result<Option<Recurrence>> {
+        match stat.read::<Option<String>, _>("recurrence")? {
+            Some(value) => match value.parse() {
+                Ok(recurrence) => Ok(Some(recurrence)),
+                Err(_) => Err(DbError::InvalidValue {
+                    id,
+                    column: "recurrence",
+                    value,
+                }),
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn read_task(stat: &Statement) -> Result<Task> {
+        let id = stat.read::<i64, _>("id")?;
+        let created = Self::read_datetime(stat, id, "created")?.ok_or(DbError::InvalidValue {
+            id,
+            column: "created",
+            value: String::new(),
+        })?;
+        let updated_at = Self::read_datetime(stat, id, "updated_at")?.ok_or(DbError::InvalidValue {
+            id,
+            column: "updated_at",
+            value: String::new(),
+        })?;
+
+        Ok(Task {
+            id,
+            uuid: Self::read_uuid(stat, id)?,
+            done: stat.read::<i64, _>("done")? == 1,
+            subject: stat.read::<String, _>("subject")?,
+            body: stat.read::<String, _>("body")?,
+            created,
+            updated_at,
+            completed_at: Self::read_datetime(stat, id, "completed_at")?,
+            due: Self::read_date(stat, id, "due")?,
+            deleted_at: Self::read_datetime(stat, id, "deleted_at")?,
+            archived_at: Self::read_datetime(stat, id, "archived_at")?,
+            position: stat.read::<i64, _>("position")?,
+            tags: vec![],
+            fields: vec![],
+            project_id: stat.read::<i64, _>("project_id")?,
+            parent_id: stat.read::<Option<i64>, _>("parent_id")?,
+            blockers: vec![],
+            blocked: false,
+            priority: Self::read_priority(stat, id)?,
+            recurrence: Self::read_recurrence(stat, id)?,
+            version: stat.read::<i64, _>("version")?,
+        })
+    }
+
+    fn read_one(&self, stat: &mut Statement) -> Result<Option<Task>> {
+        let mut task = match stat.next()? {
+            State::Row => Some(Self::read_task(stat)?),
+            State::Done => None,
+        };
+        self.load_relations(task.iter_mut())?;
+
+        Ok(task)
+    }
+
+    fn read_all(&self, stat: &mut Statement) -> Result<Vec<Task>> {
+        let mut tasks = vec![];
+        while let State::Row = stat.next()? {
+            tasks.push(Self::read_task(stat)?);
+        }
+        self.load_relations(tasks.iter_mut())?;
+
+        Ok(tasks)
+    }
+
+    fn load_relations<'t, I>(&self, tasks: I) -> Result<()>
+    where
+        I: IntoIterator<Item = &'t mut Task>,
+    {
+        let mut tasks: HashMap<i64, &mut Task> =
+            tasks.into_iter().map(|task| (task.id, task)).collect();
+        if tasks.is_empty() {
+            return Ok(());
+        }
+
+        let ids = tasks.keys().map(|id| id.to_string()).collect::<Vec<_>>();
+        let ids = format!("[{}]", ids.join(","));
+        let sql = "
+            SELECT task_tags.task_id, tags.name FROM task_tags
+            JOIN tags ON tags.id = task_tags.tag_id
+            WHERE task_tags.task_id IN (SELECT value FROM json_each(?))
+            ORDER BY tags.name COLLATE NOCASE;
+        ";
+        let mut stat = self.connection.prepare(sql)?;
+        stat.bind((1, ids.as_str()))?;
+        while let State::Row = stat.next()? {
+            let id = stat.read::<i64, _>("task_id")?;
+            if let Some(task) = tasks.get_mut(&id) {
+                task.tags.push(stat.read::<String, _>("name")?);
+            }
+        }
 
-        let exists = Path::new(file).exists();
-        let connection = sqlite::open(file).unwrap();
-        let db = Db { connection };
+        let sql = "
+            SELECT task_id, name, kind, value FROM fields
+            WHERE task_id IN (SELECT value FROM json_each(?))
+            ORDER BY name COLLATE NOCASE;
+        ";
+        let mut stat = self.connection.prepare(sql)?;
+        stat.bind((1, ids.as_str()))?;
+        while let State::Row = stat.next()? {
+            let id = stat.read::<i64, _>("task_id")?;
+            let name = stat.read::<String, _>("name")?;
+            let value = stat.read::<Value, _>("value")?;
+            let value = FieldValue::from_value(&stat.read::<String, _>("kind")?, value).ok_or_else(|| {
+                DbError::InvalidValue {
+                    id,
+                    column: "fields",
+                    value: name.clone(),
+                }
+            })?;
+            if let Some(task) = tasks.get_mut(&id) {
+                task.fields.push(Field { name, value });
+            }
+        }
 
-        if !exists {
-            db.init_tables();
+        let sql = "
+            SELECT dependencies.task_id, dependencies.blocker_id,
+                blockers.done = 0 AND blockers.deleted_at IS NULL AS open
+            FROM dependencies
+            JOIN tasks AS blockers ON blockers.id = dependencies.blocker_id
+            WHERE dependencies.task_id IN (SELECT value FROM json_each(?))
+            ORDER BY dependencies.blocker_id;
+        ";
+        let mut stat = self.connection.prepare(sql)?;
+        stat.bind((1, ids.as_str()))?;
+        while let State::Row = stat.next()? {
+            let id = stat.read::<i64, _>("task_id")?;
+            if let Some(task) = tasks.get_mut(&id) {
+                task.blockers.push(stat.read::<i64, _>("blocker_id")?);
+                task.blocked |= stat.read::<i64, _>("open")? == 1;
+            }
         }
 
-        db
+        Ok(())
     }
 
-    fn init_tables(&self) {
+    fn set_tags(&self, id: i64, tags: &[String]) -> Result<()> {
+        let mut stat = self.connection.prepare("DELETE FROM task_tags WHERE task_id = ?;")?;
+        stat.bind((1, id))?;
+        stat.next()?;
+
+        let sql = "INSERT INTO tags (name) VALUES (?) ON CONFLICT (name) DO NOTHING;";
+        let mut insert_tag = self.connection.prepare(sql)?;
         let sql = "
-            CREATE TABLE tasks (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                done BOOLEAN NOT NULL DEFAULT 0,
-                subject VARCHAR(50) NOT NULL,
-                body TEXT NOT NULL DEFAULT '',
-                created DATETIME DEFAULT CURRENT_TIMESTAMP,
-                due DATETIME NOT NULL DEFAULT ''
-            );
+            INSERT OR IGNORE INTO task_tags (task_id, tag_id)
+            SELECT ?, id FROM tags WHERE name = ?;
         ";
-        self.connection.execute(sql).unwrap();
+        let mut insert_task_tag = self.connection.prepare(sql)?;
+        for tag in tags {
+            insert_tag.reset()?;
+            insert_tag.bind((1, tag.as_str()))?;
+            insert_tag.next()?;
+            insert_task_tag.reset()?;
+            insert_task_tag.bind((1, id))?;
+            insert_task_tag.bind((2, tag.as_str()))?;
+            insert_task_tag.next()?;
+        }
+
+        self.delete_unused_tags()
     }
 
-    pub fn insert_one(&self, subject: &str, body: &str) -> Option<Task> {
+    fn set_fields(&self, id: i64, fields: &[Field]) -> Result<()> {
+        let mut stat = self.connection.prepare("DELETE FROM fields WHERE task_id = ?;")?;
+        stat.bind((1, id))?;
+        stat.next()?;
+
+        let sql = "INSERT OR REPLACE INTO fields (task_id, name, kind, value) VALUES (?, ?, ?, ?);";
+        let mut stat = self.connection.prepare(sql)?;
+        for field in fields {
+            stat.reset()?;
+            stat.bind((1, id))?;
+            stat.bind((2, field.name.as_str()))?;
+            stat.bind((3, field.value.kind()))?;
+            stat.bind((4, &field.value.to_value()))?;
+            stat.next()?;
+        }
+
+        Ok(())
+    }
+
+    fn set_blockers(&self, id: i64, blockers: &[i64]) -> Result<()> {
+        let mut stat = self.connection.prepare("DELETE FROM dependencies WHERE task_id = ?;")?;
+        stat.bind((1, id))?;
+        stat.next()?;
+        for blocker_id in blockers {
+            self.insert_dependency(id, *blocker_id)?;
+        }
+
+        Ok(())
+    }
+
+    fn insert_dependency(&self, id: i64, blocker_id: i64) -> Result<bool> {
         let sql = "
-            INSERT INTO tasks (subject, body)
-            VALUES (?, ?)
-            RETURNING *;
+            WITH RECURSIVE blockers (id) AS (
+                SELECT ?
+                UNION
+                SELECT dependencies.blocker_id FROM dependencies
+                JOIN blockers ON dependencies.task_id = blockers.id
+            )
+            SELECT COUNT(*) FROM blockers WHERE id = ?;
         ";
-        let mut stat = self.connection.prepare(sql).unwrap();
-        stat.bind((1, subject)).unwrap();
-        stat.bind((2, body)).unwrap();
-        while let Ok(State::Row) = stat.next() {
-            return Some(Task {
-                id: stat.read::<i64, _>("id").unwrap(),
-                done: stat.read::<i64, _>("done").unwrap() == 1,
-                subject: stat.read::<String, _>("subject").unwrap(),
-                body: stat.read::<String, _>("body").unwrap(),
-                created: stat.read::<String, _>("created").unwrap(),
-                due: stat.read::<String, _>("due").unwrap(),
-            });
+        let mut stat = self.connection.prepare(sql)?;
+        stat.bind((1, blocker_id))?;
+        stat.bind((2, id))?;
+        stat.next()?;
+        if stat.read::<i64, _>(0)? > 0 {
+            return Err(DbError::DependencyCycle { id, blocker_id });
         }
 
-        None
+        let sql = "
+            INSERT OR IGNORE INTO dependencies (task_id, blocker_id)
+            SELECT ?, id FROM tasks WHERE id = ?;
+        ";
+        let mut stat = self.connection.prepare(sql)?;
+        stat.bind((1, id))?;
+        stat.bind((2, blocker_id))?;
+        stat.next()?;
+
+        Ok(self.connection.change_count() > 0)
+    }
+
+    fn bump_version(&self, id: i64) -> Result<()> {
+        let sql = "UPDATE tasks SET version = version + 1, updated_at = CURRENT_TIMESTAMP WHERE id = ?;";
+        let mut stat = self.connection.prepare(sql)?;
+        stat.bind((1, id))?;
+        stat.next()?;
+        Ok(())
     }
 
-    pub fn delete_one(&self, id: i64) -> Option<Task> {
+    fn delete_unused_tags(&self) -> Result<()> {
+        let sql = "DELETE FROM tags WHERE id NOT IN (SELECT tag_id FROM task_tags);";
+        self.connection.execute(sql)?;
+        Ok(())
+    }
+
+    fn check_parent(&self, id: i64, parent_id: i64) -> Result<()> {
         let sql = "
-            DELETE FROM tasks
-            WHERE id = ?
-            RETURNING *;
+            WITH RECURSIVE ancestors (id) AS (
+                SELECT ?
+                UNION
+                SELECT tasks.parent_id FROM tasks
+                JOIN ancestors ON tasks.id = ancestors.id
+                WHERE tasks.parent_id IS NOT NULL
+            )
+            SELECT COUNT(*) FROM ancestors WHERE id = ?;
         ";
-        let mut stat = self.connection.prepare(sql).unwrap();
-        stat.bind((1, id)).unwrap();
-        while let Ok(State::Row) = stat.next() {
-            return Some(Task {
-                id: stat.read::<i64, _>("id").unwrap(),
-                done: stat.read::<i64, _>("done").unwrap() == 1,
-                subject: stat.read::<String, _>("subject").unwrap(),
-                body: stat.read::<String, _>("body").unwrap(),
-                created: stat.read::<String, _>("created").unwrap(),
-                due: stat.read::<String, _>("due").unwrap(),
-            });
+        let mut stat = self.connection.prepare(sql)?;
+        stat.bind((1, parent_id))?;
+        stat.bind((2, id))?;
+        stat.next()?;
+        if stat.read::<i64, _>(0)? > 0 {
+            return Err(DbError::ParentCycle { id, parent_id });
         }
 
-        None
+        Ok(())
     }
 
-    pub fn get_one(&self, id: i64) -> Option<Task> {
-        let sql = "SELECT * FROM tasks WHERE id = ?";
-        let mut stat = self.connection.prepare(sql).unwrap();
-        stat.bind((1, id)).unwrap();
-        while let Ok(State::Row) = stat.next() {
-            return Some(Task {
-                id: stat.read::<i64, _>("id").unwrap(),
-                done: stat.read::<i64, _>("done").unwrap() == 1,
-                subject: stat.read::<String, _>("subject").unwrap(),
-                body: stat.read::<String, _>("body").unwrap(),
-                created: stat.read::<String, _>("created").unwrap(),
-                due: stat.read::<String, _>("due").unwrap(),
-            });
+    fn read_project(stat: &Statement) -> Result<Project> {
+        Ok(Project {
+            id: stat.read::<i64, _>("id")?,
+            name: stat.read::<String, _>("name")?,
+            archived: stat.read::<i64, _>("archived")? == 1,
+        })
+    }
+
+    fn check_project_name(&self, id: i64, name: &str) -> Result<()> {
+        let sql = "SELECT COUNT(*) FROM projects WHERE name = ? AND id != ?;";
+        let mut stat = self.connection.prepare(sql)?;
+        stat.bind((1, name))?;
+        stat.bind((2, id))?;
+        stat.next()?;
+        if stat.read::<i64, _>(0)? > 0 {
+            return Err(DbError::ProjectExists(name.to_string()));
         }
 
-        None
+        Ok(())
+    }
+
+    fn read_time_entry(stat: &Statement) -> Result<TimeEntry> {
+        let id = stat.read::<i64, _>("id")?;
+        let started_at = Self::read_datetime(stat, id, "started_at")?.ok_or(DbError::InvalidValue {
+            id,
+            column: "started_at",
+            value: String::new(),
+        })?;
+
+        Ok(TimeEntry {
+            id,
+            task_id: stat.read::<i64, _>("task_id")?,
+            started_at,
+            stopped_at: Self::read_datetime(stat, id, "stopped_at")?,
+        })
     }
 
-    pub fn get_next(&self, id: i64) -> Option<Task> {
+    fn reorder<F>(&self, id: i64, target: F) -> Result<Option<(usize, usize)>>
+    where
+        F: FnOnce(&[i64]) -> Option<usize>,
+    {
+        self.transaction(|| {
+            // only the visible siblings move, into the positions they already hold
+            let sql = "
+                SELECT siblings.id, siblings.position FROM tasks
+                JOIN tasks AS siblings
+                    ON siblings.project_id = tasks.project_id AND siblings.parent_id IS tasks.parent_id
+                WHERE tasks.id = ? AND siblings.deleted_at IS NULL AND siblings.archived_at IS NULL
+                ORDER BY siblings.position, siblings.id;
+            ";
+            let mut stat = self.connection.prepare(sql)?;
+            stat.bind((1, id))?;
+            let (mut ids, mut positions) = (vec![], vec![]);
+            while let State::Row = stat.next()? {
+                ids.push(stat.read::<i64, _>("id")?);
+                positions.push(stat.read::<i64, _>("position")?);
+            }
+
+            let from = match ids.iter().position(|&i| i == id) {
+                Some(from) => from,
+                None => return Ok(None),
+            };
+            ids.remove(from);
+            let to = match target(&ids) {
+                Some(to) => to.min(ids.len()),
+                None => return Ok(None),
+            };
+            ids.insert(to, id);
+
+            let sql = "
+                UPDATE tasks
+                SET position = ?1, version = version + 1, updated_at = CURRENT_TIMESTAMP
+                WHERE id = ?2 AND position != ?1;
+            ";
+            let mut stat = self.connection.prepare(sql)?;
+            for (position, id) in positions.iter().zip(&ids) {
+                stat.reset()?;
+                stat.bind((1, *position))?;
+                stat.bind((2, *id))?;
+                stat.next()?;
+            }
+
+            Ok(Some((from, to)))
+        })
+    }
+}
+
+impl TaskStore for Db {
+    fn get_one(&self, id: i64) -> Result<Option<Task>> {
+        let sql = "SELECT * FROM tasks WHERE id = ?";
+        let mut stat = self.connection.prepare(sql)?;
+        stat.bind((1, id))?;
+        self.read_one(&mut stat)
+    }
+
+    fn get_by_uuid(&self, uuid: &Uuid) -> Result<Option<Task>> {
+        let sql = "SELECT * FROM tasks WHERE uuid = ?";
+        let mut stat = self.connection.prepare(sql)?;
+        stat.bind((1, uuid.to_string().as_str()))?;
+        self.read_one(&mut stat)
+    }
+
+    fn insert_one(&self, project_id: i64, subject: &str, body: &str) -> Result<Option<Task>> {
         let sql = "
-            SELECT * FROM tasks
-            WHERE id > ?
-            ORDER BY id ASC
-            LIMIT 1;
+            INSERT INTO tasks (uuid, project_id, subject, body, position, updated_at)
+            VALUES (?, ?, ?, ?, (SELECT COALESCE(MAX(position) + 1, 0) FROM tasks), CURRENT_TIMESTAMP)
+            RETURNING *;
+        ";
+        let mut stat = self.connection.prepare(sql)?;
+        stat.bind((1, Uuid::new_v4().to_string().as_str()))?;
+        stat.bind((2, project_id))?;
+        stat.bind((3, subject))?;
+        stat.bind((4, body))?;
+        self.read_one(&mut stat)
+    }
+
+    fn insert_task(&self, task: &Task) -> Result<Option<Task>> {
+        self.transaction(|| {
+            let sql = "
+                INSERT INTO tasks (
+                    id, uuid, done, subject, body, created, due, deleted_at, archived_at, position,
+                    project_id, parent_id, priority, recurrence, completed_at, updated_at, version
+                )
+                VALUES (
+                    :id, :uuid, :done, :subject, :body, :created, :due, :deleted_at, :archived_at, :position,
+                    :project_id, :parent_id, :priority, :recurrence, :completed_at, :updated_at, :version
+                );
+            ";
+            let mut stat = self.connection.prepare(sql)?;
+            stat.bind((":id", task.id))?;
+            stat.bind((":uuid", task.uuid.to_string().as_str()))?;
+            stat.bind((":done", if task.done { 1 } else { 0 }))?;
+            stat.bind((":subject", task.subject.as_str()))?;
+            stat.bind((":body", task.body.as_str()))?;
+            let created = task.created.format(DATETIME_FORMAT).to_string();
+            stat.bind((":created", created.as_str()))?;
+            let due = task.due.map(|due| due.format(DATE_FORMAT).to_string());
+            stat.bind((":due", due.as_deref()))?;
+            let deleted_at = task
+                .deleted_at
+                .map(|deleted_at| deleted_at.format(DATETIME_FORMAT).to_string());
+            stat.bind((":deleted_at", deleted_at.as_deref()))?;
+            let archived_at = task
+                .archived_at
+                .map(|archived_at| archived_at.format(DATETIME_FORMAT).to_string());
+            stat.bind((":archived_at", archived_at.as_deref()))?;
+            stat.bind((":position", task.position))?;
+            stat.bind((":project_id", task.project_id))?;
+            stat.bind((":parent_id", task.parent_id))?;
+            stat.bind((":priority", task.priority as i64))?;
+            let recurrence = task.recurrence.as_ref().map(Recurrence::to_string);
+            stat.bind((":recurrence", recurrence.as_deref()))?;
+            let completed_at = task
+                .completed_at
+                .map(|completed_at| completed_at.format(DATETIME_FORMAT).to_string());
+            stat.bind((":completed_at", completed_at.as_deref()))?;
+            let updated_at = task.updated_at.format(DATETIME_FORMAT).to_string();
+            stat.bind((":updated_at", updated_at.as_str()))?;
+            stat.bind((":version", task.version))?;
+            stat.next()?;
+
+            self.set_tags(task.id, &task.tags)?;
+            self.set_fields(task.id, &task.fields)?;
+            self.set_blockers(task.id, &task.blockers)?;
+            self.get_one(task.id)
+        })
+    }
+
+    fn update_one(&self, task: &Task) -> Result<Option<Task>> {
+        self.transaction(|| {
+            if let Some(parent_id) = task.parent_id {
+                self.check_parent(task.id, parent_id)?;
+            }
+            let sql = "
+                INSERT INTO revisions (task_id, subject, body)
+                SELECT id, subject, body FROM tasks
+                WHERE id = ? AND (subject != ? OR body != ?);
+            ";
+            let mut stat = self.connection.prepare(sql)?;
+            stat.bind((1, task.id))?;
+            stat.bind((2, task.subject.as_str()))?;
+            stat.bind((3, task.body.as_str()))?;
+            stat.next()?;
+
+            let sql = "
+                UPDATE tasks
+                SET done = :done,
+                    subject = :subject,
+                    body = :body,
+                    due = :due,
+                    project_id = :project_id,
+                    parent_id = :parent_id,
+                    priority = :priority,
+                    recurrence = :recurrence,
+                    completed_at = CASE
+                        WHEN NOT :done THEN NULL
+                        WHEN done THEN completed_at
+                        ELSE CURRENT_TIMESTAMP
+                    END,
+                    updated_at = CURRENT_TIMESTAMP,
+                    version = version + 1
+                WHERE id = :id AND version = :version;
+            ";
+            let mut stat = self.connection.prepare(sql)?;
+            stat.bind((":id", task.id))?;
+            stat.bind((":done", if task.done { 1 } else { 0 }))?;
+            stat.bind((":subject", task.subject.as_str()))?;
+            stat.bind((":body", task.body.as_str()))?;
+            let due = task.due.map(|due| due.format(DATE_FORMAT).to_string());
+            stat.bind((":due", due.as_deref()))?;
+            stat.bind((":project_id", task.project_id))?;
+            stat.bind((":parent_id", task.parent_id))?;
+            stat.bind((":priority", task.priority as i64))?;
+            let recurrence = task.recurrence.as_ref().map(Recurrence::to_string);
+            stat.bind((":recurrence", recurrence.as_deref()))?;
+            stat.bind((":version", task.version))?;
+            stat.next()?;
+            if self.connection.change_count() == 0 {
+                return match self.get_one(task.id)? {
+                    Some(_) => Err(DbError::StaleTask(task.id)),
+                    None => Ok(None),
+                };
+            }
+
+            self.set_tags(task.id, &task.tags)?;
+            self.set_fields(task.id, &task.fields)?;
+            self.set_blockers(task.id, &task.blockers)?;
+            self.get_one(task.id)
+        })
+    }
+
+    fn delete_one(&self, id: i64) -> Result<Option<Task>> {
+        self.transaction(|| {
+            let task = self.get_one(id)?;
+            let mut stat = self.connection.prepare("DELETE FROM tasks WHERE id = ?;")?;
+            stat.bind((1, id))?;
+            stat.next()?;
+            self.delete_unused_tags()?;
+
+            Ok(task)
+        })
+    }
+
+    fn trash_one(&self, id: i64) -> Result<Option<Task>> {
+        let sql = "
+            UPDATE tasks
+            SET deleted_at = CURRENT_TIMESTAMP, version = version + 1, updated_at = CURRENT_TIMESTAMP
+            WHERE id = ? AND deleted_at IS NULL
+            RETURNING *;
+        ";
+        let mut stat = self.connection.prepare(sql)?;
+        stat.bind((1, id))?;
+        self.read_one(&mut stat)
+    }
+
+    fn restore_one(&self, id: i64) -> Result<Option<Task>> {
+        let sql = "
+            UPDATE tasks
+            SET deleted_at = NULL, version = version + 1, updated_at = CURRENT_TIMESTAMP
+            WHERE id = ? AND deleted_at IS NOT NULL
+            RETURNING *;
+        ";
+        let mut stat = self.connection.prepare(sql)?;
+        stat.bind((1, id))?;
+        self.read_one(&mut stat)
+    }
+
+    fn purge_one(&self, id: i64) -> Result<Option<Task>> {
+        self.transaction(|| match self.get_one(id)? {
+            Some(task) if task.deleted_at.is_some() => self.delete_one(id),
+            _ => Ok(None),
+        })
+    }
+
+    fn archive_one(&self, id: i64) -> Result<Option<Task>> {
+        let sql = "
+            UPDATE tasks
+            SET archived_at = CURRENT_TIMESTAMP, version = version + 1, updated_at = CURRENT_TIMESTAMP
+            WHERE id = ? AND archived_at IS NULL
+            RETURNING *;
         ";
-        let mut stat = self.connection.prepare(sql).unwrap();
-        stat.bind((1, id)).unwrap();
-        while let Ok(State::Row) = stat.next() {
-            return Some(Task {
-                id: stat.read::<i64, _>("id").unwrap(),
-                done: stat.read::<i64, _>("done").unwrap() == 1,
-                subject: stat.read::<String, _>("subject").unwrap(),
-                body: stat.read::<String, _>("body").unwrap(),
-                created: stat.read::<String, _>("created").unwrap(),
-                due: stat.read::<String, _>("due").unwrap(),
+        let mut stat = self.connection.prepare(sql)?;
+        stat.bind((1, id))?;
+        self.read_one(&mut stat)
+    }
+
+    fn unarchive_one(&self, id: i64) -> Result<Option<Task>> {
+        let sql = "
+            UPDATE tasks
+            SET archived_at = NULL, version = version + 1, updated_at = CURRENT_TIMESTAMP
+            WHERE id = ? AND archived_at IS NOT NULL
+            RETURNING *;
+        ";
+        let mut stat = self.connection.prepare(sql)?;
+        stat.bind((1, id))?;
+        self.read_one(&mut stat)
+    }
+
+    fn query(&self, query: &TaskQuery) -> Result<Vec<Task>> {
+        let (filter, mut values) = query.filter(true);
+        let (order_by, order_values) = query.order_by();
+        values.extend(order_values);
+        let sql = format!(
+            "
+            SELECT * FROM tasks
+            WHERE {}
+            ORDER BY {}
+            {};
+            ",
+            filter,
+            order_by,
+            query.page(),
+        );
+        let mut stat = self.connection.prepare(sql)?;
+        stat.bind(&values[..])?;
+        self.read_all(&mut stat)
+    }
+
+    fn search(&self, query: &TaskQuery) -> Result<Vec<SearchHit>> {
+        let text = match query.match_text() {
+            Some(text) => text,
+            None => return Ok(vec![]),
+        };
+
+        let (filter, values) = query.filter(false);
+        let sql = format!(
+            "
+            SELECT tasks.*,
+                highlight(tasks_fts, 0, char(2), char(3)) AS subject_match,
+                highlight(tasks_fts, 1, char(2), char(3)) AS body_match,
+                snippet(tasks_fts, 1, char(2), char(3), '...', 8) AS snippet
+            FROM tasks_fts
+            JOIN tasks ON tasks.id = tasks_fts.rowid
+            WHERE tasks_fts MATCH ? AND {}
+            ORDER BY bm25(tasks_fts, 10.0, 1.0), tasks.position
+            {};
+            ",
+            filter,
+            query.page(),
+        );
+        let mut stat = self.connection.prepare(sql)?;
+        stat.bind((1, text.as_str()))?;
+        for (i, value) in values.iter().enumerate() {
+            stat.bind((i + 2, value))?;
+        }
+
+        let mut hits = vec![];
+        while let State::Row = stat.next()? {
+            hits.push(SearchHit {
+                task: Self::read_task(&stat)?,
+                subject: stat.read::<String, _>("subject_match")?,
+                body: stat.read::<String, _>("body_match")?,
+                snippet: stat.read::<String, _>("snippet")?,
             });
         }
+        self.load_relations(hits.iter_mut().map(|hit| &mut hit.task))?;
 
-        None
+        Ok(hits)
     }
 
-    pub fn get_prev(&self, id: i64) -> Option<Task> {
+    fn list_trash(&self) -> Result<Vec<Task>> {
         let sql = "
             SELECT * FROM tasks
-            WHERE id < ?
-            ORDER BY id DESC
-            LIMIT 1;
+            WHERE deleted_at IS NOT NULL
+            ORDER BY deleted_at DESC, id DESC;
         ";
-        let mut stat = self.connection.prepare(sql).unwrap();
-        stat.bind((1, id)).unwrap();
-        while let Ok(State::Row) = stat.next() {
-            return Some(Task {
-                id: stat.read::<i64, _>("id").unwrap(),
-                done: stat.read::<i64, _>("done").unwrap() == 1,
-                subject: stat.read::<String, _>("subject").unwrap(),
-                body: stat.read::<String, _>("body").unwrap(),
-                created: stat.read::<String, _>("created").unwrap(),
-                due: stat.read::<String, _>("due").unwrap(),
-            });
-        }
+        let mut stat = self.connection.prepare(sql)?;
+        self.read_all(&mut stat)
+    }
 
-        None
+    fn list_archive(&self, project_id: i64) -> Result<Vec<Task>> {
+        let sql = "
+            SELECT * FROM tasks
+            WHERE archived_at IS NOT NULL AND deleted_at IS NULL AND project_id = ?
+            ORDER BY archived_at DESC, id DESC;
+        ";
+        let mut stat = self.connection.prepare(sql)?;
+        stat.bind((1, project_id))?;
+        self.read_all(&mut stat)
     }
 
-    pub fn update_one(&self, task: &Task) -> Option<Task> {
+    fn archive_done(&self, project_id: Option<i64>, days: u32) -> Result<Vec<Task>> {
         let sql = "
             UPDATE tasks
-            SET done = :done,
-                subject = :subject,
-                body = :body,
-                due = :due
-            WHERE id = :id
+            SET archived_at = CURRENT_TIMESTAMP, version = version + 1, updated_at = CURRENT_TIMESTAMP
+            WHERE done AND archived_at IS NULL AND deleted_at IS NULL
+                AND COALESCE(completed_at, updated_at) <= datetime('now', ?)
+                AND (? IS NULL OR project_id = ?)
             RETURNING *;
         ";
-        let mut stat = self.connection.prepare(sql).unwrap();
-        stat.bind((":id", task.id)).unwrap();
-        stat.bind((":done", if task.done { 1 } else { 0 })).unwrap();
-        stat.bind((":subject", task.subject.as_str())).unwrap();
-        stat.bind((":body", task.body.as_str())).unwrap();
-        stat.bind((":due", task.due.as_str())).unwrap();
-        while let Ok(State::Row) = stat.next() {
-            return Some(Task {
-                id: stat.read::<i64, _>("id").unwrap(),
-                done: stat.read::<i64, _>("done").unwrap() == 1,
-                subject: stat.read::<String, _>("subject").unwrap(),
-                body: stat.read::<String, _>("body").unwrap(),
-                created: stat.read::<String, _>("created").unwrap(),
-                due: stat.read::<String, _>("due").unwrap(),
+        let mut stat = self.connection.prepare(sql)?;
+        stat.bind((1, format!("-{} days", days).as_str()))?;
+        stat.bind((2, project_id))?;
+        stat.bind((3, project_id))?;
+        self.read_all(&mut stat)
+    }
+
+    fn purge_trash(&self, days: u32) -> Result<usize> {
+        let sql = "
+            DELETE FROM tasks
+            WHERE deleted_at <= datetime('now', ?);
+        ";
+        let mut stat = self.connection.prepare(sql)?;
+        stat.bind((1, format!("-{} days", days).as_str()))?;
+        stat.next()?;
+        Ok(self.connection.change_count())
+    }
+
+    fn move_to(&self, id: i64, index: usize) -> Result<Option<(usize, usize)>> {
+        self.reorder(id, |_| Some(index))
+    }
+
+    fn move_before(&self, id: i64, other: i64) -> Result<Option<(usize, usize)>> {
+        self.reorder(id, |ids| ids.iter().position(|&i| i == other))
+    }
+
+    fn move_after(&self, id: i64, other: i64) -> Result<Option<(usize, usize)>> {
+        self.reorder(id, |ids| ids.iter().position(|&i| i == other).map(|i| i + 1))
+    }
+
+    fn children(&self, id: i64) -> Result<Vec<Task>> {
+        let sql = "
+            SELECT * FROM tasks
+            WHERE deleted_at IS NULL AND parent_id = ?
+            ORDER BY position, id;
+        ";
+        let mut stat = self.connection.prepare(sql)?;
+        stat.bind((1, id))?;
+        self.read_all(&mut stat)
+    }
+
+    fn move_under(&self, id: i64, parent_id: Option<i64>) -> Result<Option<Task>> {
+        self.transaction(|| match self.get_one(id)? {
+            Some(mut task) => {
+                task.parent_id = parent_id;
+                self.update_one(&task)
+            }
+            None => Ok(None),
+        })
+    }
+
+    fn add_dependency(&self, id: i64, blocker_id: i64) -> Result<Option<Task>> {
+        self.transaction(|| {
+            if self.insert_dependency(id, blocker_id)? {
+                self.bump_version(id)?;
+            }
+            self.get_one(id)
+        })
+    }
+
+    fn remove_dependency(&self, id: i64, blocker_id: i64) -> Result<Option<Task>> {
+        self.transaction(|| {
+            let sql = "DELETE FROM dependencies WHERE task_id = ? AND blocker_id = ?;";
+            let mut stat = self.connection.prepare(sql)?;
+            stat.bind((1, id))?;
+            stat.bind((2, blocker_id))?;
+            stat.next()?;
+            if self.connection.change_count() > 0 {
+                self.bump_version(id)?;
+            }
+            self.get_one(id)
+        })
+    }
+
+    fn dependents(&self, id: i64) -> Result<Vec<Task>> {
+        let sql = "
+            SELECT tasks.* FROM tasks
+            JOIN dependencies ON dependencies.task_id = tasks.id
+            WHERE dependencies.blocker_id = ? AND tasks.deleted_at IS NULL
+            ORDER BY tasks.position, tasks.id;
+        ";
+        let mut stat = self.connection.prepare(sql)?;
+        stat.bind((1, id))?;
+        self.read_all(&mut stat)
+    }
+
+    fn spawn_next(&self, task: &Task, completed: NaiveDate) -> Result<Option<Task>> {
+        let due = match &task.recurrence {
+            Some(recurrence) => recurrence.next(task.due, completed),
+            None => None,
+        };
+        if due.is_none() {
+            return Ok(None);
+        }
+
+        self.transaction(|| match self.insert_one(task.project_id, &task.subject, &task.body)? {
+            Some(mut next) => {
+                next.due = due;
+                next.tags = task.tags.clone();
+                next.parent_id = task.parent_id;
+                next.priority = task.priority;
+                next.recurrence = task.recurrence.as_ref().map(|recurrence| recurrence.anchored(task.due));
+                self.update_one(&next)
+            }
+            None => Ok(None),
+        })
+    }
+
+    fn revisions(&self, task_id: i64) -> Result<Vec<Revision>> {
+        let sql = "SELECT * FROM revisions WHERE task_id = ? ORDER BY id DESC;";
+        let mut stat = self.connection.prepare(sql)?;
+        stat.bind((1, task_id))?;
+        let mut revisions = vec![];
+        while let State::Row = stat.next()? {
+            let id = stat.read::<i64, _>("id")?;
+            let created = Self::read_datetime(&stat, id, "created")?.ok_or(DbError::InvalidValue {
+                id,
+                column: "created",
+                value: String::new(),
+            })?;
+            revisions.push(Revision {
+                id,
+                task_id: stat.read::<i64, _>("task_id")?,
+                subject: stat.read::<String, _>("subject")?,
+                body: stat.read::<String, _>("body")?,
+                created,
             });
         }
 
-        None
+        Ok(revisions)
     }
 
-    pub fn list(&self) -> Vec<Task> {
-        let mut tasks = vec![];
+    fn data_version(&self) -> Result<i64> {
+        let mut stat = self.connection.prepare("PRAGMA data_version;")?;
+        stat.next()?;
+        Ok(stat.read::<i64, _>(0)?)
+    }
 
-        let sql = "SELECT * FROM tasks;";
-        let mut stat = self.connection.prepare(sql).unwrap();
-        while let Ok(State::Row) = stat.next() {
-            let task = Task {
-                id: stat.read::<i64, _>("id").unwrap(),
-                done: stat.read::<i64, _>("done").unwrap() == 1,
-                subject: stat.read::<String, _>("subject").unwrap(),
-                body: stat.read::<String, _>("body").unwrap(),
-                created: stat.read::<String, _>("created").unwrap(),
-                due: stat.read::<String, _>("due").unwrap(),
-            };
-            tasks.push(task);
+    fn get_project(&self, id: i64) -> Result<Option<Project>> {
+        let mut stat = self.connection.prepare("SELECT * FROM projects WHERE id = ?;")?;
+        stat.bind((1, id))?;
+        match stat.next()? {
+            State::Row => Ok(Some(Self::read_project(&stat)?)),
+            State::Done => Ok(None),
+        }
+    }
+
+    fn list_projects(&self) -> Result<Vec<Project>> {
+        let sql = "
+            SELECT * FROM projects
+            ORDER BY archived, id = 1 DESC, name COLLATE NOCASE;
+        ";
+        let mut stat = self.connection.prepare(sql)?;
+        let mut projects = vec![];
+        while let State::Row = stat.next()? {
+            projects.push(Self::read_project(&stat)?);
+        }
+
+        Ok(projects)
+    }
+
+    fn create_project(&self, name: &str) -> Result<Project> {
+        self.transaction(|| {
+            self.check_project_name(0, name)?;
+            let sql = "INSERT INTO projects (name) VALUES (?) RETURNING *;";
+            let mut stat = self.connection.prepare(sql)?;
+            stat.bind((1, name))?;
+            stat.next()?;
+            Self::read_project(&stat)
+        })
+    }
+
+    fn rename_project(&self, id: i64, name: &str) -> Result<Option<Project>> {
+        self.transaction(|| {
+            self.check_project_name(id, name)?;
+            let mut stat = self.connection.prepare("UPDATE projects SET name = ? WHERE id = ?;")?;
+            stat.bind((1, name))?;
+            stat.bind((2, id))?;
+            stat.next()?;
+            self.get_project(id)
+        })
+    }
+
+    fn archive_project(&self, id: i64, archived: bool) -> Result<Option<Project>> {
+        let mut stat = self.connection.prepare("UPDATE projects SET archived = ? WHERE id = ?;")?;
+        stat.bind((1, if archived { 1 } else { 0 }))?;
+        stat.bind((2, id))?;
+        stat.next()?;
+        self.get_project(id)
+    }
+
+    fn delete_project(&self, id: i64) -> Result<Option<Project>> {
+        if id == DEFAULT_PROJECT {
+            return Err(DbError::DefaultProject);
+        }
+
+        self.transaction(|| {
+            let project = self.get_project(id)?;
+            let sql = "
+                UPDATE tasks
+                SET project_id = ?, version = version + 1, updated_at = CURRENT_TIMESTAMP
+                WHERE project_id = ?;
+            ";
+            let mut stat = self.connection.prepare(sql)?;
+            stat.bind((1, DEFAULT_PROJECT))?;
+            stat.bind((2, id))?;
+            stat.next()?;
+            let mut stat = self.connection.prepare("DELETE FROM projects WHERE id = ?;")?;
+            stat.bind((1, id))?;
+            stat.next()?;
+
+            Ok(project)
+        })
+    }
+
+    fn running_timer(&self) -> Result<Option<TimeEntry>> {
+        let sql = "SELECT * FROM time_entries WHERE stopped_at IS NULL;";
+        let mut stat = self.connection.prepare(sql)?;
+        match stat.next()? {
+            State::Row => Ok(Some(Self::read_time_entry(&stat)?)),
+            State::Done => Ok(None),
+        }
+    }
+
+    fn start_timer(&self, task_id: i64) -> Result<TimeEntry> {
+        self.transaction(|| {
+            self.stop_timer()?;
+            let sql = "INSERT INTO time_entries (task_id) VALUES (?) RETURNING *;";
+            let mut stat = self.connection.prepare(sql)?;
+            stat.bind((1, task_id))?;
+            stat.next()?;
+            Self::read_time_entry(&stat)
+        })
+    }
+
+    fn stop_timer(&self) -> Result<Option<TimeEntry>> {
+        let sql = "
+            UPDATE time_entries
+            SET stopped_at = MAX(started_at, CURRENT_TIMESTAMP)
+            WHERE stopped_at IS NULL
+            RETURNING *;
+        ";
+        let mut stat = self.connection.prepare(sql)?;
+        match stat.next()? {
+            State::Row => Ok(Some(Self::read_time_entry(&stat)?)),
+            State::Done => Ok(None),
+        }
+    }
+
+    fn tracked(&self, task_id: i64) -> Result<i64> {
+        let sql = "
+            SELECT COALESCE(SUM(
+                strftime('%s', COALESCE(stopped_at, CURRENT_TIMESTAMP)) - strftime('%s', started_at)
+            ), 0)
+            FROM time_entries
+            WHERE task_id = ?;
+        ";
+        let mut stat = self.connection.prepare(sql)?;
+        stat.bind((1, task_id))?;
+        stat.next()?;
+        Ok(stat.read::<i64, _>(0)?)
+    }
+
+    fn time_report(&self) -> Result<Vec<TimeReport>> {
+        let sql = "
+            SELECT date(time_entries.started_at, 'localtime') AS day,
+                tasks.id AS task_id,
+                tasks.subject,
+                SUM(
+                    strftime('%s', COALESCE(time_entries.stopped_at, CURRENT_TIMESTAMP))
+                    - strftime('%s', time_entries.started_at)
+                ) AS seconds
+            FROM time_entries
+            JOIN tasks ON tasks.id = time_entries.task_id
+            GROUP BY day, tasks.id
+            ORDER BY day, seconds DESC, tasks.id;
+        ";
+        let mut stat = self.connection.prepare(sql)?;
+        let mut report = vec![];
+        while let State::Row = stat.next()? {
+            let task_id = stat.read::<i64, _>("task_id")?;
+            let day = Self::read_date(&stat, task_id, "day")?.ok_or(DbError::InvalidValue {
+                id: task_id,
+                column: "day",
+                value: String::new(),
+            })?;
+            report.push(TimeReport {
+                day,
+                task_id,
+                subject: stat.read::<String, _>("subject")?,
+                seconds: stat.read::<i64, _>("seconds")?,
+            });
         }
 
-        tasks
+        Ok(report)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::store::MemoryStore;
+    use chrono::Local;
+
+    fn memory() -> Db {
+        Db::with_connection(sqlite::open(":memory:").unwrap()).unwrap()
+    }
+
+    fn search(db: &Db, project_id: i64, text: &str) -> Result<Vec<SearchHit>> {
+        db.search(&TaskQuery::new().project(project_id).text(text))
+    }
+
+    fn subjects(db: &Db) -> Vec<String> {
+        db.query(&TaskQuery::new().project(DEFAULT_PROJECT))
+            .unwrap()
+            .into_iter()
+            .map(|task| task.subject)
+            .collect()
+    }
 
     #[test]
     fn test_insert_one() {
         let subject = "test_subject";
         let body = "test_body";
 
-        let db = Db::new();
-        let insert = db.insert_one(subject, body);
+        let db = memory();
+        let insert = db.insert_one(DEFAULT_PROJECT, subject, body).unwrap();
         if let Some(task) = insert {
             assert_eq!(task.subject, subject);
             assert_eq!(task.body, body);
         } else {
-            assert!(false, "failed to insert task!");
+            panic!("failed to insert task!");
         }
     }
 
@@ -232,21 +1306,20 @@ mod tests {
         let subject = "test_subject";
         let body = "test_body";
 
-        let db = Db::new();
-        let insert = db.insert_one(subject, body);
+        let db = memory();
+        let insert = db.insert_one(DEFAULT_PROJECT, subject, body).unwrap();
         if let Some(task) = insert {
             id = task.id;
         } else {
-            assert!(false, "failed to insert task!");
-            return;
+            panic!("failed to insert task!");
         }
 
-        let get = db.get_one(id);
+        let get = db.get_one(id).unwrap();
         if let Some(task) = get {
             assert_eq!(task.subject, subject);
             assert_eq!(task.body, body);
         } else {
-            assert!(false, "failed to get task!");
+            panic!("failed to get task!");
         }
     }
 
@@ -257,24 +1330,729 @@ mod tests {
         let new_subject = "new_test_subject";
         let new_body = "new_test_body";
 
-        let db = Db::new();
-        let insert = db.insert_one(subject, body);
+        let db = memory();
+        let insert = db.insert_one(DEFAULT_PROJECT, subject, body).unwrap();
         let mut inserted;
         if let Some(task) = insert {
             inserted = task;
         } else {
-            assert!(false, "failed to insert task!");
-            return;
+            panic!("failed to insert task!");
         }
 
         inserted.subject = new_subject.to_string();
         inserted.body = new_body.to_string();
-        let update = db.update_one(&inserted);
+        let update = db.update_one(&inserted).unwrap();
         if let Some(task) = update {
             assert_eq!(task.subject, new_subject);
             assert_eq!(task.body, new_body);
         } else {
-            assert!(false, "failed to update task!");
+            panic!("failed to update task!");
+        }
+    }
+
+    #[test]
+    fn test_update_missing_one() {
+        let db = memory();
+        let task = Task {
+            id: 1,
+            uuid: Uuid::new_v4(),
+            done: false,
+            subject: String::from("test_subject"),
+            body: String::new(),
+            created: Utc::now(),
+            updated_at: Utc::now(),
+            completed_at: None,
+            due: None,
+            deleted_at: None,
+            archived_at: None,
+            position: 0,
+            tags: vec![],
+            fields: vec![],
+            project_id: DEFAULT_PROJECT,
+            parent_id: None,
+            blockers: vec![],
+            blocked: false,
+            priority: Priority::None,
+            recurrence: None,
+            version: 0,
+        };
+        assert!(db.update_one(&task).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_migrate_new_database() {
+        let db = memory();
+        assert_eq!(db.user_version().unwrap(), SCHEMA_VERSION);
+        assert!(db.query(&TaskQuery::new().project(DEFAULT_PROJECT)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_migrate_unversioned_database() {
+        let connection = sqlite::open(":memory:").unwrap();
+        connection
+            .execute(
+                "
+                CREATE TABLE tasks (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    done BOOLEAN NOT NULL DEFAULT 0,
+                    subject VARCHAR(50) NOT NULL,
+                    body TEXT NOT NULL DEFAULT '',
+                    created DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    due DATETIME NOT NULL DEFAULT ''
+                );
+                INSERT INTO tasks (id, subject) VALUES (11, 'old_subject');
+                INSERT INTO tasks (id, subject, due) VALUES (12, 'older_subject', '2025/01/31');
+                INSERT INTO tasks (id, subject, due) VALUES (13, 'deleted_subject', '2025/02/30');
+                DELETE FROM tasks WHERE id = 13;
+                ",
+            )
+            .unwrap();
+
+        let db = Db::with_connection(connection).unwrap();
+        assert_eq!(db.user_version().unwrap(), SCHEMA_VERSION);
+        assert_eq!(subjects(&db), ["old_subject", "older_subject"]);
+        let tasks = db.query(&TaskQuery::new().project(DEFAULT_PROJECT)).unwrap();
+        assert_eq!(tasks[0].due, None);
+        assert_eq!(tasks[1].due, NaiveDate::from_ymd_opt(2025, 1, 31));
+        assert_eq!(tasks[0].uuid.get_version_num(), 4);
+        assert_ne!(tasks[0].uuid, tasks[1].uuid);
+        let task = db.insert_one(DEFAULT_PROJECT, "new_subject", "").unwrap().unwrap();
+        assert_eq!(task.id, 14);
+        assert_eq!(subjects(&db), ["old_subject", "older_subject", "new_subject"]);
+    }
+
+    #[test]
+    fn test_migrate_done_task() {
+        let connection = sqlite::open(":memory:").unwrap();
+        connection
+            .execute(
+                "
+                CREATE TABLE tasks (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    done BOOLEAN NOT NULL DEFAULT 0,
+                    subject VARCHAR(50) NOT NULL,
+                    body TEXT NOT NULL DEFAULT '',
+                    created DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    due DATETIME NOT NULL DEFAULT ''
+                );
+                INSERT INTO tasks (subject, done, created) VALUES ('done_subject', 1, '2025-01-31 12:00:00');
+                INSERT INTO tasks (subject) VALUES ('open_subject');
+                ",
+            )
+            .unwrap();
+
+        let db = Db::with_connection(connection).unwrap();
+        let tasks = db.query(&TaskQuery::new().project(DEFAULT_PROJECT)).unwrap();
+        assert_eq!(tasks[0].completed_at, Some(tasks[0].created));
+        assert_eq!(tasks[1].completed_at, None);
+        assert!(db.diagnose().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_failed_commit_rolls_back() {
+        let db = memory();
+        let task = db.insert_one(DEFAULT_PROJECT, "a", "").unwrap().unwrap();
+        let result = db.transaction(|| {
+            db.connection.execute("PRAGMA defer_foreign_keys = ON;")?;
+            let sql = format!("INSERT INTO dependencies (task_id, blocker_id) VALUES ({}, 42);", task.id);
+            Ok(db.connection.execute(sql)?)
+        });
+        assert!(result.is_err());
+
+        assert!(db.transaction(|| Ok(())).is_ok());
+        assert!(db.get_one(task.id).unwrap().unwrap().blockers.is_empty());
+    }
+
+    #[test]
+    fn test_failed_rollback_keeps_error() {
+        let db = memory();
+        let result: Result<()> = db.transaction(|| {
+            db.connection.execute("ROLLBACK;")?;
+            Err(DbError::StaleTask(42))
+        });
+        assert!(matches!(result, Err(DbError::StaleTask(42))));
+        assert!(db.transaction(|| Ok(())).is_ok());
+    }
+
+    #[test]
+    fn test_uuids() {
+        let connection = sqlite::open(":memory:").unwrap();
+        connection
+            .execute(
+                "
+                CREATE TABLE tasks (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    done BOOLEAN NOT NULL DEFAULT 0,
+                    subject VARCHAR(50) NOT NULL,
+                    body TEXT NOT NULL DEFAULT '',
+                    created DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    due DATETIME NOT NULL DEFAULT ''
+                );
+                INSERT INTO tasks (subject) VALUES ('a'), ('b'), ('c');
+                ",
+            )
+            .unwrap();
+
+        let db = Db::with_connection(connection).unwrap();
+        let tasks = db.query(&TaskQuery::new()).unwrap();
+        let mut uuids: Vec<_> = tasks.iter().map(|task| task.uuid).collect();
+        assert!(uuids.iter().all(|uuid| uuid.get_version_num() == 4));
+        uuids.sort();
+        uuids.dedup();
+        assert_eq!(uuids.len(), 3);
+
+        let stores: [Box<dyn TaskStore>; 2] = [Box::new(db), Box::new(MemoryStore::new())];
+        for store in stores {
+            let task = store.insert_one(DEFAULT_PROJECT, "d", "").unwrap().unwrap();
+            let found = store.get_by_uuid(&task.uuid).unwrap().unwrap();
+            assert_eq!((found.id, found.uuid), (task.id, task.uuid));
+            assert!(store.get_by_uuid(&Uuid::new_v4()).unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn test_migrate_newer_database() {
+        let connection = sqlite::open(":memory:").unwrap();
+        let sql = format!("PRAGMA user_version = {};", SCHEMA_VERSION + 1);
+        connection.execute(sql).unwrap();
+
+        let result = Db::with_connection(connection);
+        assert!(matches!(result, Err(DbError::SchemaTooNew { .. })));
+    }
+
+    #[test]
+    fn test_move() {
+        let db = memory();
+        let a = db.insert_one(DEFAULT_PROJECT, "a", "").unwrap().unwrap();
+        let b = db.insert_one(DEFAULT_PROJECT, "b", "").unwrap().unwrap();
+        let c = db.insert_one(DEFAULT_PROJECT, "c", "").unwrap().unwrap();
+
+        assert_eq!(db.move_to(c.id, 0).unwrap(), Some((2, 0)));
+        assert_eq!(subjects(&db), ["c", "a", "b"]);
+        assert_eq!(db.move_after(c.id, b.id).unwrap(), Some((0, 2)));
+        assert_eq!(subjects(&db), ["a", "b", "c"]);
+        assert_eq!(db.move_before(a.id, c.id).unwrap(), Some((0, 1)));
+        assert_eq!(subjects(&db), ["b", "a", "c"]);
+        assert_eq!(db.move_to(b.id, 99).unwrap(), Some((0, 2)));
+        assert_eq!(subjects(&db), ["a", "c", "b"]);
+
+        assert!(db.move_before(a.id, 99).unwrap().is_none());
+        assert!(db.move_to(99, 0).unwrap().is_none());
+        assert_eq!(subjects(&db), ["a", "c", "b"]);
+
+        let ids: Vec<_> = db.query(&TaskQuery::new().project(DEFAULT_PROJECT)).unwrap().iter().map(|task| task.id).collect();
+        assert_eq!(ids, [a.id, c.id, b.id]);
+    }
+
+    #[test]
+    fn test_update_due() {
+        let db = memory();
+        let mut task = db.insert_one(DEFAULT_PROJECT, "test_subject", "").unwrap().unwrap();
+        assert_eq!(task.due, None);
+
+        task.due = NaiveDate::from_ymd_opt(2025, 2, 28);
+        let task = db.update_one(&task).unwrap().unwrap();
+        assert_eq!(task.due, NaiveDate::from_ymd_opt(2025, 2, 28));
+    }
+
+    #[test]
+    fn test_invalid_due() {
+        let db = memory();
+        let task = db.insert_one(DEFAULT_PROJECT, "test_subject", "").unwrap().unwrap();
+        let sql = format!("UPDATE tasks SET due = '2025/01/01' WHERE id = {};", task.id);
+        db.connection.execute(sql).unwrap();
+
+        let result = db.query(&TaskQuery::new().project(DEFAULT_PROJECT));
+        assert!(matches!(result, Err(DbError::InvalidValue { column: "due", .. })));
+    }
+
+    #[test]
+    fn test_trash_then_restore_one() {
+        let db = memory();
+        let a = db.insert_one(DEFAULT_PROJECT, "a", "").unwrap().unwrap();
+        let b = db.insert_one(DEFAULT_PROJECT, "b", "").unwrap().unwrap();
+
+        let trashed = db.trash_one(a.id).unwrap().unwrap();
+        assert!(trashed.deleted_at.is_some());
+        assert!(db.trash_one(a.id).unwrap().is_none());
+        assert_eq!(subjects(&db), ["b"]);
+        assert_eq!(db.list_trash().unwrap()[0].id, a.id);
+
+        assert!(db.purge_one(b.id).unwrap().is_none());
+        let restored = db.restore_one(a.id).unwrap().unwrap();
+        assert!(restored.deleted_at.is_none());
+        assert_eq!(subjects(&db), ["a", "b"]);
+        assert!(db.list_trash().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_purge() {
+        let db = memory();
+        let a = db.insert_one(DEFAULT_PROJECT, "a", "").unwrap().unwrap();
+        let b = db.insert_one(DEFAULT_PROJECT, "b", "").unwrap().unwrap();
+        let c = db.insert_one(DEFAULT_PROJECT, "c", "").unwrap().unwrap();
+        db.trash_one(a.id).unwrap();
+        db.trash_one(b.id).unwrap();
+        db.trash_one(c.id).unwrap();
+        let sql = format!(
+            "UPDATE tasks SET deleted_at = datetime('now', '-10 days') WHERE id = {};",
+            a.id,
+        );
+        db.connection.execute(sql).unwrap();
+
+        assert_eq!(db.purge_trash(7).unwrap(), 1);
+        assert!(db.get_one(a.id).unwrap().is_none());
+
+        assert!(db.purge_one(b.id).unwrap().is_some());
+        assert!(db.get_one(b.id).unwrap().is_none());
+        assert_eq!(db.list_trash().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_archive_without_completed_at() {
+        let db = memory();
+        let mut task = db.insert_one(DEFAULT_PROJECT, "a", "").unwrap().unwrap();
+        task.done = true;
+        db.update_one(&task).unwrap().unwrap();
+        let sql = format!(
+            "UPDATE tasks SET completed_at = NULL, updated_at = datetime('now', '-10 days') WHERE id = {};",
+            task.id,
+        );
+        db.connection.execute(sql).unwrap();
+
+        assert_eq!(db.archive_done(None, 7).unwrap().len(), 1);
+        assert!(db.get_one(task.id).unwrap().unwrap().archived_at.is_some());
+    }
+
+    #[test]
+    fn test_delete_then_insert_task() {
+        let db = memory();
+        db.insert_one(DEFAULT_PROJECT, "a", "").unwrap().unwrap();
+        let mut b = db.insert_one(DEFAULT_PROJECT, "b", "body").unwrap().unwrap();
+        db.insert_one(DEFAULT_PROJECT, "c", "").unwrap().unwrap();
+        b.due = NaiveDate::from_ymd_opt(2025, 1, 1);
+        b.tags = vec![String::from("work")];
+        let b = db.update_one(&b).unwrap().unwrap();
+
+        let deleted = db.delete_one(b.id).unwrap().unwrap();
+        assert_eq!(subjects(&db), ["a", "c"]);
+
+        let task = db.insert_task(&deleted).unwrap().unwrap();
+        assert_eq!(subjects(&db), ["a", "b", "c"]);
+        assert_eq!(task.id, b.id);
+        assert_eq!(task.body, b.body);
+        assert_eq!(task.created, b.created);
+        assert_eq!(task.due, b.due);
+        assert_eq!(task.tags, b.tags);
+    }
+
+    #[test]
+    fn test_search() {
+        let db = memory();
+        let a = db.insert_one(DEFAULT_PROJECT, "write report", "numbers for the quarterly meeting").unwrap().unwrap();
+        let b = db.insert_one(DEFAULT_PROJECT, "book flights", "before the quarterly review").unwrap().unwrap();
+        let c = db.insert_one(DEFAULT_PROJECT, "quarterly taxes", "").unwrap().unwrap();
+        db.insert_one(DEFAULT_PROJECT, "unrelated", "").unwrap().unwrap();
+
+        let hits = search(&db, DEFAULT_PROJECT, "quarter").unwrap();
+        let ids: Vec<_> = hits.iter().map(|hit| hit.task.id).collect();
+        assert_eq!(ids[0], c.id);
+        assert_eq!(ids.len(), 3);
+        assert_eq!(hits[0].subject, "\u{2}quarterly\u{3} taxes");
+        let hit = hits.iter().find(|hit| hit.task.id == b.id).unwrap();
+        assert_eq!(hit.body, "before the \u{2}quarterly\u{3} review");
+
+        let hits = search(&db, DEFAULT_PROJECT, "quarterly meet").unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].task.id, a.id);
+
+        db.trash_one(c.id).unwrap();
+        let mut a = a;
+        a.subject = String::from("write summary");
+        a.body = String::new();
+        db.update_one(&a).unwrap();
+        let hits = search(&db, DEFAULT_PROJECT, "quarterly").unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].task.id, b.id);
+        assert_eq!(search(&db, DEFAULT_PROJECT, "summary").unwrap()[0].task.id, a.id);
+
+        db.delete_one(b.id).unwrap();
+        assert!(search(&db, DEFAULT_PROJECT, "quarterly").unwrap().is_empty());
+        assert!(search(&db, DEFAULT_PROJECT, "\" OR ( *").unwrap().is_empty());
+        assert!(search(&db, DEFAULT_PROJECT, "   ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_tags() {
+        let db = memory();
+        let mut a = db.insert_one(DEFAULT_PROJECT, "a", "").unwrap().unwrap();
+        let mut b = db.insert_one(DEFAULT_PROJECT, "b", "").unwrap().unwrap();
+        db.insert_one(DEFAULT_PROJECT, "c", "").unwrap().unwrap();
+
+        a.tags = vec![String::from("work"), String::from("Urgent")];
+        let a = db.update_one(&a).unwrap().unwrap();
+        assert_eq!(a.tags, ["Urgent", "work"]);
+        b.tags = vec![String::from("Work"), String::from("work")];
+        let b = db.update_one(&b).unwrap().unwrap();
+        assert_eq!(b.tags, ["work"]);
+
+        let tasks = db.query(&TaskQuery::new().project(DEFAULT_PROJECT)).unwrap();
+        assert_eq!(tasks[0].tags, ["Urgent", "work"]);
+        assert_eq!(tasks[1].tags, ["work"]);
+        assert!(tasks[2].tags.is_empty());
+
+        let tagged = |tags: &[&str]| -> Vec<i64> {
+            let tags: Vec<_> = tags.iter().map(|tag| tag.to_string()).collect();
+            db.query(&TaskQuery::new().project(DEFAULT_PROJECT).tags(&tags)).unwrap().iter().map(|task| task.id).collect()
+        };
+        assert_eq!(tagged(&["work"]), [a.id, b.id]);
+        assert_eq!(tagged(&["WORK", "urgent"]), [a.id]);
+        assert!(tagged(&["home"]).is_empty());
+
+        db.trash_one(a.id).unwrap();
+        assert_eq!(tagged(&["work"]), [b.id]);
+        db.purge_one(a.id).unwrap();
+        let count = |sql: &str| {
+            let mut stat = db.connection.prepare(sql).unwrap();
+            stat.next().unwrap();
+            stat.read::<i64, _>(0).unwrap()
+        };
+        assert_eq!(count("SELECT COUNT(*) FROM task_tags;"), 1);
+        assert_eq!(count("SELECT COUNT(*) FROM tags;"), 1);
+    }
+
+    #[test]
+    fn test_projects() {
+        let db = memory();
+        let projects = db.list_projects().unwrap();
+        assert_eq!(projects.len(), 1);
+        assert_eq!(projects[0].name, "todo");
+
+        let work = db.create_project("work").unwrap();
+        let home = db.create_project("home").unwrap();
+        assert!(matches!(db.create_project("Work"), Err(DbError::ProjectExists(_))));
+        assert!(matches!(db.rename_project(home.id, "WORK"), Err(DbError::ProjectExists(_))));
+        assert_eq!(db.rename_project(home.id, "house").unwrap().unwrap().name, "house");
+
+        db.archive_project(work.id, true).unwrap();
+        let names: Vec<_> = db.list_projects().unwrap().into_iter().map(|p| p.name).collect();
+        assert_eq!(names, ["todo", "house", "work"]);
+
+        let a = db.insert_one(DEFAULT_PROJECT, "a", "").unwrap().unwrap();
+        let mut b = db.insert_one(work.id, "b", "").unwrap().unwrap();
+        assert_eq!(subjects(&db), ["a"]);
+        assert_eq!(db.query(&TaskQuery::new().project(work.id)).unwrap()[0].id, b.id);
+
+        b.project_id = home.id;
+        db.update_one(&b).unwrap();
+        assert!(db.query(&TaskQuery::new().project(work.id)).unwrap().is_empty());
+        assert_eq!(search(&db, home.id, "b").unwrap()[0].task.id, b.id);
+
+        assert!(matches!(db.delete_project(DEFAULT_PROJECT), Err(DbError::DefaultProject)));
+        assert!(db.delete_project(home.id).unwrap().is_some());
+        assert!(db.get_project(home.id).unwrap().is_none());
+        assert_eq!(subjects(&db), ["a", "b"]);
+        assert_eq!(a.project_id, DEFAULT_PROJECT);
+    }
+
+    #[test]
+    fn test_subtasks() {
+        let db = memory();
+        let a = db.insert_one(DEFAULT_PROJECT, "a", "").unwrap().unwrap();
+        let b = db.insert_one(DEFAULT_PROJECT, "b", "").unwrap().unwrap();
+        let c = db.insert_one(DEFAULT_PROJECT, "c", "").unwrap().unwrap();
+
+        assert_eq!(db.move_under(c.id, Some(a.id)).unwrap().unwrap().parent_id, Some(a.id));
+        db.move_under(b.id, Some(a.id)).unwrap();
+        db.move_under(c.id, Some(b.id)).unwrap();
+        let children: Vec<_> = db.children(a.id).unwrap().into_iter().map(|t| t.subject).collect();
+        assert_eq!(children, ["b"]);
+        assert_eq!(db.children(b.id).unwrap()[0].id, c.id);
+
+        assert!(matches!(db.move_under(a.id, Some(c.id)), Err(DbError::ParentCycle { .. })));
+        assert!(matches!(db.move_under(a.id, Some(a.id)), Err(DbError::ParentCycle { .. })));
+        assert!(db.move_under(42, None).unwrap().is_none());
+
+        db.trash_one(b.id).unwrap();
+        assert!(db.children(a.id).unwrap().is_empty());
+        db.purge_one(b.id).unwrap();
+        assert_eq!(db.get_one(c.id).unwrap().unwrap().parent_id, None);
+    }
+
+    #[test]
+    fn test_dependencies() {
+        let db = memory();
+        let a = db.insert_one(DEFAULT_PROJECT, "a", "").unwrap().unwrap();
+        let b = db.insert_one(DEFAULT_PROJECT, "b", "").unwrap().unwrap();
+        let c = db.insert_one(DEFAULT_PROJECT, "c", "").unwrap().unwrap();
+
+        let b = db.add_dependency(b.id, a.id).unwrap().unwrap();
+        assert_eq!(b.blockers, [a.id]);
+        assert!(b.blocked);
+        db.add_dependency(c.id, b.id).unwrap();
+        assert!(matches!(db.add_dependency(a.id, c.id), Err(DbError::DependencyCycle { .. })));
+        assert!(matches!(db.add_dependency(a.id, a.id), Err(DbError::DependencyCycle { .. })));
+        assert_eq!(db.dependents(a.id).unwrap()[0].id, b.id);
+
+        let mut a = db.get_one(a.id).unwrap().unwrap();
+        a.done = true;
+        db.update_one(&a).unwrap();
+        assert!(!db.get_one(b.id).unwrap().unwrap().blocked);
+        assert!(db.get_one(c.id).unwrap().unwrap().blocked);
+
+        db.trash_one(b.id).unwrap();
+        assert!(!db.get_one(c.id).unwrap().unwrap().blocked);
+        assert!(db.dependents(b.id).unwrap()[0].blockers == [b.id]);
+        db.purge_one(b.id).unwrap();
+        assert!(db.get_one(c.id).unwrap().unwrap().blockers.is_empty());
+
+        let c = db.remove_dependency(c.id, b.id).unwrap().unwrap();
+        assert!(c.blockers.is_empty());
+    }
+
+    #[test]
+    fn test_priority() {
+        let db = memory();
+        for (subject, priority, due) in [
+            ("a", Priority::None, Some("2025-01-01")),
+            ("b", Priority::High, Some("2025-03-01")),
+            ("c", Priority::High, None),
+            ("d", Priority::Low, Some("2025-02-01")),
+        ] {
+            let mut task = db.insert_one(DEFAULT_PROJECT, subject, "").unwrap().unwrap();
+            task.priority = priority;
+            task.due = due.map(|due| NaiveDate::parse_from_str(due, DATE_FORMAT).unwrap());
+            db.update_one(&task).unwrap();
+        }
+
+        let sorted = |sort| -> Vec<String> {
+            db.query(&TaskQuery::new().project(DEFAULT_PROJECT).sort(sort)).unwrap().into_iter().map(|task| task.subject).collect()
+        };
+        assert_eq!(sorted(Sort::Position), ["a", "b", "c", "d"]);
+        assert_eq!(sorted(Sort::Priority), ["b", "c", "d", "a"]);
+        assert_eq!(sorted(Sort::Due), ["a", "d", "b", "c"]);
+
+        assert_eq!(Priority::None.lower(), Priority::None);
+        assert_eq!(Priority::Medium.raise(), Priority::High);
+        assert_eq!(Priority::High.raise(), Priority::High);
+
+        db.connection.execute("UPDATE tasks SET priority = 7 WHERE subject = 'a';").unwrap();
+        assert!(matches!(
+            db.query(&TaskQuery::new().project(DEFAULT_PROJECT)),
+            Err(DbError::InvalidValue { column: "priority", .. })
+        ));
+    }
+
+    #[test]
+    fn test_recurrence() {
+        let db = memory();
+        let mut task = db.insert_one(DEFAULT_PROJECT, "report", "weekly").unwrap().unwrap();
+        task.due = NaiveDate::from_ymd_opt(2025, 1, 13);
+        task.tags = vec![String::from("work")];
+        task.recurrence = Some("FREQ=WEEKLY;BYDAY=MO".parse().unwrap());
+        let task = db.update_one(&task).unwrap().unwrap();
+        assert_eq!(task.recurrence.as_ref().unwrap().to_string(), "FREQ=WEEKLY;BYDAY=MO");
+
+        let next = db.spawn_next(&task, NaiveDate::from_ymd_opt(2025, 1, 14).unwrap());
+        let next = next.unwrap().unwrap();
+        assert_ne!(next.id, task.id);
+        assert_eq!(next.subject, "report");
+        assert_eq!(next.due, NaiveDate::from_ymd_opt(2025, 1, 20));
+        assert_eq!(next.tags, ["work"]);
+        assert_eq!(next.recurrence, task.recurrence);
+
+        let once = db.insert_one(DEFAULT_PROJECT, "once", "").unwrap().unwrap();
+        assert!(db.spawn_next(&once, NaiveDate::from_ymd_opt(2025, 1, 14).unwrap()).unwrap().is_none());
+
+        db.connection.execute("UPDATE tasks SET recurrence = 'FREQ=SOMETIMES';").unwrap();
+        assert!(matches!(
+            db.get_one(once.id),
+            Err(DbError::InvalidValue { column: "recurrence", .. })
+        ));
+    }
+
+    #[test]
+    fn test_time_entries() {
+        let db = memory();
+        let a = db.insert_one(DEFAULT_PROJECT, "a", "").unwrap().unwrap();
+        let b = db.insert_one(DEFAULT_PROJECT, "b", "").unwrap().unwrap();
+        assert!(db.running_timer().unwrap().is_none());
+        assert!(db.stop_timer().unwrap().is_none());
+
+        db.start_timer(a.id).unwrap();
+        let entry = db.start_timer(b.id).unwrap();
+        assert_eq!(db.running_timer().unwrap().unwrap().id, entry.id);
+        assert_eq!(db.tracked(a.id).unwrap(), 0);
+        let sql = "INSERT INTO time_entries (task_id, started_at) VALUES (?, ?);";
+        assert!(db.connection.prepare(sql).and_then(|mut stat| {
+            stat.bind((1, a.id))?;
+            stat.bind((2, "2025-01-01 00:00:00"))?;
+            stat.next()
+        }).is_err());
+        let entry = db.stop_timer().unwrap().unwrap();
+        assert_eq!(entry.task_id, b.id);
+        assert!(entry.stopped_at.is_some());
+
+        db.connection
+            .execute(
+                "
+                INSERT INTO time_entries (task_id, started_at, stopped_at) VALUES
+                    (1, '2025-01-15 11:00:00', '2025-01-15 12:30:00'),
+                    (2, '2025-01-15 12:00:00', '2025-01-15 12:45:00'),
+                    (1, '2025-01-16 12:00:00', '2025-01-16 12:10:00');
+                ",
+            )
+            .unwrap();
+        assert_eq!(db.tracked(a.id).unwrap(), 100 * 60);
+
+        let report: Vec<_> = db
+            .time_report()
+            .unwrap()
+            .into_iter()
+            .filter(|row| row.seconds > 0)
+            .map(|row| (row.day.format(DATE_FORMAT).to_string(), row.subject, row.seconds))
+            .collect();
+        assert_eq!(
+            report,
+            [
+                (String::from("2025-01-15"), String::from("a"), 90 * 60),
+                (String::from("2025-01-15"), String::from("b"), 45 * 60),
+                (String::from("2025-01-16"), String::from("a"), 10 * 60),
+            ]
+        );
+
+        db.purge_one(db.trash_one(a.id).unwrap().unwrap().id).unwrap();
+        assert_eq!(db.tracked(a.id).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_timestamps() {
+        let db = memory();
+        let task = db.insert_one(DEFAULT_PROJECT, "a", "").unwrap().unwrap();
+        assert_eq!(task.updated_at, task.created);
+        assert!(task.completed_at.is_none());
+
+        db.connection
+            .execute("UPDATE tasks SET created = '2025-01-01 00:00:00', updated_at = created;")
+            .unwrap();
+        let mut task = db.get_one(task.id).unwrap().unwrap();
+        task.done = true;
+        let done = db.update_one(&task).unwrap().unwrap();
+        assert!(done.updated_at > done.created);
+        let completed_at = done.completed_at.unwrap();
+
+        db.connection.execute("UPDATE tasks SET completed_at = '2025-01-02 00:00:00';").unwrap();
+        let mut task = done;
+        task.subject = String::from("edited");
+        let edited = db.update_one(&task).unwrap().unwrap();
+        assert!(edited.completed_at.unwrap() < completed_at);
+
+        let mut task = edited;
+        task.done = false;
+        assert!(db.update_one(&task).unwrap().unwrap().completed_at.is_none());
+    }
+
+    #[test]
+    fn test_revisions() {
+        let db = memory();
+        let mut task = db.insert_one(DEFAULT_PROJECT, "draft", "v1").unwrap().unwrap();
+        task.done = true;
+        let mut task = db.update_one(&task).unwrap().unwrap();
+        assert!(db.revisions(task.id).unwrap().is_empty());
+
+        task.body = String::from("v2");
+        let mut task = db.update_one(&task).unwrap().unwrap();
+        task.subject = String::from("final");
+        let task = db.update_one(&task).unwrap().unwrap();
+        let revisions: Vec<_> = db
+            .revisions(task.id)
+            .unwrap()
+            .into_iter()
+            .map(|revision| (revision.subject, revision.body))
+            .collect();
+        assert_eq!(
+            revisions,
+            [
+                (String::from("draft"), String::from("v2")),
+                (String::from("draft"), String::from("v1")),
+            ],
+        );
+
+        db.delete_one(task.id).unwrap();
+        assert!(db.revisions(task.id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_writers() {
+        let path = std::env::temp_dir().join(format!("rustodo-writers-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let a = Db::open(&path).unwrap();
+        let b = Db::open(&path).unwrap();
+        {
+            let mut stat = a.connection.prepare("PRAGMA journal_mode;").unwrap();
+            stat.next().unwrap();
+            assert_eq!(stat.read::<String, _>(0).unwrap(), "wal");
+        }
+
+        let task = a.insert_one(DEFAULT_PROJECT, "shared", "").unwrap().unwrap();
+        let version = b.data_version().unwrap();
+        let mut mine = task.clone();
+        mine.subject = String::from("mine");
+        let mine = a.update_one(&mine).unwrap().unwrap();
+        assert_eq!(mine.version, task.version + 1);
+        assert_ne!(b.data_version().unwrap(), version);
+
+        let mut theirs = task.clone();
+        theirs.subject = String::from("theirs");
+        assert!(matches!(b.update_one(&theirs), Err(DbError::StaleTask(_))));
+        assert_eq!(b.get_one(task.id).unwrap().unwrap().subject, "mine");
+        assert_eq!(b.revisions(task.id).unwrap().len(), 1);
+
+        drop((a, b));
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
         }
     }
+
+    #[test]
+    fn test_query() {
+        let db = memory();
+        let date = |day| NaiveDate::from_ymd_opt(2025, 1, day).unwrap();
+        let work = db.create_project("work").unwrap();
+        for (project_id, subject, done, due, tags) in [
+            (DEFAULT_PROJECT, "alpha report", false, Some(date(10)), vec!["work"]),
+            (DEFAULT_PROJECT, "beta report", true, Some(date(20)), vec!["work", "home"]),
+            (DEFAULT_PROJECT, "gamma", false, None, vec![]),
+            (work.id, "delta report", false, Some(date(5)), vec!["work"]),
+        ] {
+            let mut task = db.insert_one(project_id, subject, "").unwrap().unwrap();
+            task.done = done;
+            task.due = due;
+            task.tags = tags.into_iter().map(String::from).collect();
+            db.update_one(&task).unwrap();
+        }
+
+        let subjects = |query: TaskQuery| -> Vec<String> {
+            db.query(&query).unwrap().into_iter().map(|task| task.subject).collect()
+        };
+        assert_eq!(subjects(TaskQuery::new()).len(), 4);
+        assert_eq!(subjects(TaskQuery::new().project(work.id)), ["delta report"]);
+        assert_eq!(subjects(TaskQuery::new().done(true)), ["beta report"]);
+        assert_eq!(
+            subjects(TaskQuery::new().due_after(date(5)).due_before(date(20))),
+            ["alpha report"],
+        );
+        assert_eq!(subjects(TaskQuery::new().text("rep").tag("home")), ["beta report"]);
+        assert_eq!(
+            subjects(TaskQuery::new().tag("work").sort(Sort::Due).offset(1).limit(1)),
+            ["alpha report"],
+        );
+        let today = Local::now().date_naive();
+        assert_eq!(subjects(TaskQuery::new().completed_since(today)), ["beta report"]);
+        assert!(subjects(TaskQuery::new().completed_since(today + chrono::Days::new(1))).is_empty());
+
+        let hits = db.search(&TaskQuery::new().text("report").done(false).limit(2)).unwrap();
+        assert_eq!(hits.len(), 2);
+        assert!(hits.iter().all(|hit| !hit.task.done));
+    }
 }
@@ -0,0 +1,265 @@
+use std::ffi::{c_int, CStr};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::thread;
+use std::time::Duration;
+
+use chrono::Local;
+use sqlite::{Connection, OpenFlags, State};
+use sqlite3_sys as ffi;
+
+use super::{Db, DbError, Result, SCHEMA_VERSION};
+
+const BACKUP_PREFIX: &str = "tasks-";
+const BUSY_RETRIES: usize = 50;
+const REQUIRED_COLUMNS: [&str; 4] = ["id", "done", "subject", "body"];
+
+fn error(code: c_int) -> DbError {
+    // SAFETY: sqlite3_errstr returns a static, NUL-terminated string for any code, even unknown ones
+    let message = unsafe { CStr::from_ptr(ffi::sqlite3_errstr(code)) };
+    DbError::Sqlite(sqlite::Error {
+        code: Some(code as isize),
+        message: Some(message.to_string_lossy().into_owned()),
+    })
+}
+
+// finishes the backup when dropped, so the handle is released on every path
+struct Backup(*mut ffi::sqlite3_backup);
+
+impl Backup {
+    fn step(&self) -> c_int {
+        // SAFETY: the handle is live until finish or drop, and both connections outlive it
+        unsafe { ffi::sqlite3_backup_step(self.0, -1) }
+    }
+
+    fn finish(mut self) -> c_int {
+        // SAFETY: the handle is live, and nulling it below keeps drop from finishing it again
+        let code = unsafe { ffi::sqlite3_backup_finish(self.0) };
+        self.0 = ptr::null_mut();
+        code
+    }
+}
+
+impl Drop for Backup {
+    fn drop(&mut self) {
+        if !self.0.is_null() {
+            // SAFETY: the handle is live, since finish nulls it
+            unsafe { ffi::sqlite3_backup_finish(self.0) };
+        }
+    }
+}
+
+fn copy(from: &Connection, to: &Connection) -> Result<()> {
+    // SAFETY: both connections are open for the whole call, and the schema names are NUL-terminated
+    let backup =
+        unsafe { ffi::sqlite3_backup_init(to.as_raw(), c"main".as_ptr(), from.as_raw(), c"main".as_ptr()) };
+    if backup.is_null() {
+        // SAFETY: `to` is an open connection, which holds the error from sqlite3_backup_init
+        return Err(error(unsafe { ffi::sqlite3_errcode(to.as_raw()) }));
+    }
+    let backup = Backup(backup);
+
+    let mut step = backup.step();
+    for _ in 0..BUSY_RETRIES {
+        if step != ffi::SQLITE_BUSY && step != ffi::SQLITE_LOCKED {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+        step = backup.step();
+    }
+    let finish = backup.finish();
+
+    if step != ffi::SQLITE_DONE {
+        return Err(error(step));
+    }
+    if finish != ffi::SQLITE_OK {
+        return Err(error(finish));
+    }
+
+    Ok(())
+}
+
+fn check_backup(connection: &Connection) -> Result<()> {
+    let invalid = |reason: String| Err(DbError::InvalidBackup(reason));
+
+    let mut stat = connection.prepare("PRAGMA integrity_check;")?;
+    stat.next()?;
+    let integrity = stat.read::<String, _>(0)?;
+    if integrity != "ok" {
+        return invalid(format!("integrity check failed: {}", integrity));
+    }
+
+    let mut stat = connection.prepare("PRAGMA user_version;")?;
+    stat.next()?;
+    let version = stat.read::<i64, _>(0)?;
+    if version > SCHEMA_VERSION {
+        return Err(DbError::SchemaTooNew {
+            version,
+            supported: SCHEMA_VERSION,
+        });
+    }
+
+    let mut columns = vec![];
+    let mut stat = connection.prepare("SELECT name FROM pragma_table_info('tasks');")?;
+    while let State::Row = stat.next()? {
+        columns.push(stat.read::<String, _>("name")?);
+    }
+    if columns.is_empty() {
+        return invalid(String::from("no tasks table"));
+    }
+    for column in REQUIRED_COLUMNS {
+        if !columns.iter().any(|name| name == column) {
+            return invalid(format!("tasks table has no {} column", column));
+        }
+    }
+
+    Ok(())
+}
+
+fn has_tasks(connection: &Connection) -> Result<bool> {
+    let sql = "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'tasks';";
+    if let State::Done = connection.prepare(sql)?.next()? {
+        return Ok(false);
+    }
+    let mut stat = connection.prepare("SELECT 1 FROM tasks LIMIT 1;")?;
+    Ok(matches!(stat.next()?, State::Row))
+}
+
+impl Db {
+    pub fn backup<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let target = sqlite::open(path)?;
+        copy(&self.connection, &target)
+    }
+
+    pub fn restore<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let flags = OpenFlags::new().with_read_only();
+        let source = Connection::open_with_flags(path, flags)?;
+        check_backup(&source)?;
+        copy(&source, &self.connection)?;
+
+        self.connection.execute("PRAGMA foreign_keys = OFF;")?;
+        let result = self.migrate();
+        self.connection.execute("PRAGMA foreign_keys = ON;")?;
+        result
+    }
+
+    pub fn rotate_backups<P: AsRef<Path>>(&self, dir: P, keep: usize) -> Result<Option<PathBuf>> {
+        // nothing worth keeping in a new or empty database
+        if keep == 0 || !has_tasks(&self.connection)? {
+            return Ok(None);
+        }
+
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let name = format!("{}{}.db", BACKUP_PREFIX, Local::now().format("%Y%m%d-%H%M%S-%6f"));
+        let path = dir.join(name);
+        self.backup(&path)?;
+
+        let mut backups = vec![];
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+            if name.starts_with(BACKUP_PREFIX) && name.ends_with(".db") {
+                backups.push(path);
+            }
+        }
+        backups.sort();
+        for old in &backups[..backups.len().saturating_sub(keep)] {
+            fs::remove_file(old)?;
+        }
+
+        Ok(Some(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{TaskQuery, DEFAULT_PROJECT};
+    use crate::store::TaskStore;
+    use std::env;
+    use std::process;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("rustodo-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn subjects(db: &Db) -> Vec<String> {
+        db.query(&TaskQuery::new())
+            .unwrap()
+            .into_iter()
+            .map(|task| task.subject)
+            .collect()
+    }
+
+    #[test]
+    fn test_backup_then_restore() {
+        let dir = temp_dir("backup");
+        let path = dir.join("backup.db");
+
+        let db = Db::open(":memory:").unwrap();
+        db.insert_one(DEFAULT_PROJECT, "kept", "").unwrap();
+        db.backup(&path).unwrap();
+        db.insert_one(DEFAULT_PROJECT, "lost", "").unwrap();
+        assert_eq!(subjects(&db), ["kept", "lost"]);
+
+        db.restore(&path).unwrap();
+        assert_eq!(subjects(&db), ["kept"]);
+        db.insert_one(DEFAULT_PROJECT, "after", "").unwrap();
+        assert_eq!(subjects(&db), ["kept", "after"]);
+
+        let other = dir.join("other.db");
+        let connection = sqlite::open(&other).unwrap();
+        connection.execute("CREATE TABLE notes (text TEXT);").unwrap();
+        assert!(matches!(db.restore(&other), Err(DbError::InvalidBackup(_))));
+        let newer = dir.join("newer.db");
+        Db::open(&newer).unwrap().connection.execute("PRAGMA user_version = 999;").unwrap();
+        assert!(matches!(db.restore(&newer), Err(DbError::SchemaTooNew { .. })));
+        assert!(db.restore(dir.join("missing.db")).is_err());
+        assert_eq!(subjects(&db), ["kept", "after"]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_rotate_backups() {
+        let dir = temp_dir("rotate");
+        for name in ["tasks-20250101-000000.db", "tasks-20250102-000000.db", "notes.txt"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+
+        let db = Db::open(":memory:").unwrap();
+        assert!(db.rotate_backups(&dir, 2).unwrap().is_none());
+        db.insert_one(DEFAULT_PROJECT, "a", "").unwrap();
+        assert!(db.rotate_backups(&dir, 0).unwrap().is_none());
+        let first = db.rotate_backups(&dir, 3).unwrap().unwrap();
+        let path = db.rotate_backups(&dir, 2).unwrap().unwrap();
+        assert_ne!(first, path);
+
+        let mut names: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names.len(), 3);
+        assert_eq!(names[0], "notes.txt");
+        assert_eq!(dir.join(&names[1]), first);
+        assert_eq!(dir.join(&names[2]), path);
+
+        assert!(Db::open_unmigrated(dir.join("new.db")).unwrap().rotate_backups(&dir, 2).unwrap().is_none());
+        let old = dir.join("old.db");
+        let connection = sqlite::open(&old).unwrap();
+        connection.execute("CREATE TABLE tasks (id INTEGER PRIMARY KEY);").unwrap();
+        assert!(Db::open_unmigrated(&old).unwrap().rotate_backups(&dir, 2).unwrap().is_none());
+        connection.execute("INSERT INTO tasks (id) VALUES (1);").unwrap();
+        let path = Db::open_unmigrated(&old).unwrap().rotate_backups(&dir, 2).unwrap().unwrap();
+        let backup = Db::open_unmigrated(path).unwrap();
+        assert_eq!(backup.user_version().unwrap(), 0);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
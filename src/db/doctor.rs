@@ -0,0 +1,463 @@
+use std::fmt;
+
+use sqlite::State;
+use uuid::Uuid;
+
+use super::{Db, Result, DEFAULT_PROJECT, SCHEMA_VERSION};
+use crate::recurrence::Recurrence;
+
+const REPAIR_ROUNDS: usize = 3;
+
+pub struct Problem {
+    pub task_id: Option<i64>,
+    pub description: String,
+    fix: Option<Fix>,
+}
+
+enum Fix {
+    Sql { sql: String, id: Option<i64> },
+    Upgrade,
+}
+
+impl Problem {
+    pub fn fixable(&self) -> bool {
+        self.fix.is_some()
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.task_id {
+            Some(id) => write!(f, "task {}: {}", id, self.description),
+            None => write!(f, "{}", self.description),
+        }
+    }
+}
+
+macro_rules! valid_field {
+    () => {
+        "(
+            kind = 'text' AND typeof(value) = 'text'
+            OR kind = 'number' AND typeof(value) IN ('integer', 'real')
+            OR kind = 'date' AND date(value) IS value
+            OR kind = 'bool' AND value IN (0, 1)
+        )"
+    };
+}
+
+// each check selects the id and offending value of the rows it flags
+const TASK_CHECKS: &[(&str, &str, &str)] = &[
+    (
+        "created is not a timestamp",
+        "SELECT id, created AS value FROM tasks WHERE datetime(created) IS NOT created",
+        "UPDATE tasks SET created = COALESCE(datetime(created), CURRENT_TIMESTAMP) WHERE id = ?",
+    ),
+    (
+        "updated_at is not a timestamp",
+        "SELECT id, updated_at AS value FROM tasks WHERE datetime(updated_at) IS NOT updated_at",
+        "UPDATE tasks SET updated_at = COALESCE(datetime(updated_at), created) WHERE id = ?",
+    ),
+    (
+        "due is not a date",
+        "SELECT id, due AS value FROM tasks WHERE due IS NOT NULL AND date(due) IS NOT due",
+        "UPDATE tasks SET due = NULL WHERE id = ?",
+    ),
+    (
+        "deleted_at is not a timestamp",
+        "
+        SELECT id, deleted_at AS value FROM tasks
+        WHERE deleted_at IS NOT NULL AND datetime(deleted_at) IS NOT deleted_at
+        ",
+        "UPDATE tasks SET deleted_at = COALESCE(datetime(deleted_at), CURRENT_TIMESTAMP) WHERE id = ?",
+    ),
+    (
+        "archived_at is not a timestamp",
+        "
+        SELECT id, archived_at AS value FROM tasks
+        WHERE archived_at IS NOT NULL AND datetime(archived_at) IS NOT archived_at
+        ",
+        "UPDATE tasks SET archived_at = COALESCE(datetime(archived_at), CURRENT_TIMESTAMP) WHERE id = ?",
+    ),
+    (
+        "done is not 0 or 1",
+        "SELECT id, done AS value FROM tasks WHERE done IS NOT 0 AND done IS NOT 1",
+        "UPDATE tasks SET done = COALESCE(done != 0, 0) WHERE id = ?",
+    ),
+    (
+        "completed_at is not a timestamp",
+        "
+        SELECT id, completed_at AS value FROM tasks
+        WHERE completed_at IS NOT NULL AND datetime(completed_at) IS NOT completed_at
+        ",
+        "UPDATE tasks SET completed_at = datetime(completed_at) WHERE id = ?",
+    ),
+    (
+        "is done but has no completion time",
+        "SELECT id, NULL AS value FROM tasks WHERE done = 1 AND completed_at IS NULL",
+        "UPDATE tasks SET completed_at = updated_at WHERE id = ?",
+    ),
+    (
+        "is open but has a completion time",
+        "SELECT id, completed_at AS value FROM tasks WHERE done = 0 AND completed_at IS NOT NULL",
+        "UPDATE tasks SET completed_at = NULL WHERE id = ?",
+    ),
+    (
+        "priority is not between 0 and 3",
+        "SELECT id, priority AS value FROM tasks WHERE priority NOT IN (0, 1, 2, 3)",
+        "UPDATE tasks SET priority = 0 WHERE id = ?",
+    ),
+    (
+        "has a missing or circular parent",
+        "
+        SELECT id, parent_id AS value FROM tasks
+        WHERE parent_id = id OR parent_id NOT IN (SELECT id FROM tasks)
+        ",
+        "UPDATE tasks SET parent_id = NULL WHERE id = ?",
+    ),
+    (
+        "is its own ancestor",
+        "
+        WITH RECURSIVE ancestors (id, ancestor_id) AS (
+            SELECT id, parent_id FROM tasks WHERE parent_id != id
+            UNION
+            SELECT ancestors.id, tasks.parent_id FROM ancestors
+            JOIN tasks ON tasks.id = ancestors.ancestor_id
+            WHERE tasks.parent_id IS NOT NULL
+        )
+        SELECT id, NULL AS value FROM ancestors AS cycle
+        WHERE id = ancestor_id
+            AND NOT EXISTS (SELECT 1 FROM ancestors WHERE id = cycle.id AND ancestor_id < cycle.id)
+        ",
+        "UPDATE tasks SET parent_id = NULL WHERE id = ?",
+    ),
+    (
+        "is blocked by itself through a dependency cycle",
+        "
+        WITH RECURSIVE blockers (id, blocker_id) AS (
+            SELECT task_id, blocker_id FROM dependencies
+            UNION
+            SELECT blockers.id, dependencies.blocker_id FROM blockers
+            JOIN dependencies ON dependencies.task_id = blockers.blocker_id
+        )
+        SELECT id, NULL AS value FROM blockers AS cycle
+        WHERE id = blocker_id
+            AND NOT EXISTS (
+                SELECT 1 FROM blockers
+                JOIN blockers AS back ON back.id = blockers.blocker_id AND back.blocker_id = blockers.id
+                WHERE blockers.id = cycle.id AND blockers.blocker_id < cycle.id
+            )
+        ",
+        "
+        WITH RECURSIVE dependents (id) AS (
+            SELECT ?1
+            UNION
+            SELECT dependencies.task_id FROM dependencies
+            JOIN dependents ON dependencies.blocker_id = dependents.id
+        )
+        DELETE FROM dependencies WHERE task_id = ?1 AND blocker_id IN (SELECT id FROM dependents)
+        ",
+    ),
+    (
+        "has an invalid custom field",
+        concat!(
+            "SELECT task_id AS id, name || '=' || value AS value FROM fields WHERE NOT ",
+            valid_field!(),
+        ),
+        concat!("DELETE FROM fields WHERE task_id = ? AND NOT ", valid_field!()),
+    ),
+];
+
+impl Db {
+    pub fn diagnose(&self) -> Result<Vec<Problem>> {
+        let mut problems = vec![];
+
+        let mut stat = self.connection.prepare("PRAGMA integrity_check;")?;
+        while let State::Row = stat.next()? {
+            let result = stat.read::<String, _>(0)?;
+            if result != "ok" {
+                problems.push(Problem {
+                    task_id: None,
+                    description: format!("integrity check: {}", result),
+                    fix: None,
+                });
+            }
+        }
+
+        // the checks below need the current schema, which only repair upgrades to
+        let version = self.user_version()?;
+        if version != SCHEMA_VERSION {
+            problems.push(Problem {
+                task_id: None,
+                description: format!("schema is at version {}, expected {}", version, SCHEMA_VERSION),
+                fix: (version < SCHEMA_VERSION).then_some(Fix::Upgrade),
+            });
+            return Ok(problems);
+        }
+
+        for (description, check, fix) in TASK_CHECKS {
+            let mut stat = self.connection.prepare(*check)?;
+            while let State::Row = stat.next()? {
+                let id = stat.read::<i64, _>("id")?;
+                problems.push(Problem {
+                    task_id: Some(id),
+                    description: match stat.read::<Option<String>, _>("value")? {
+                        Some(value) => format!("{}: {:?}", description, value),
+                        None => description.to_string(),
+                    },
+                    fix: Some(Fix::Sql {
+                        sql: fix.to_string(),
+                        id: Some(id),
+                    }),
+                });
+            }
+        }
+
+        let sql = "SELECT id, project_id FROM tasks WHERE project_id NOT IN (SELECT id FROM projects);";
+        let mut stat = self.connection.prepare(sql)?;
+        while let State::Row = stat.next()? {
+            let id = stat.read::<i64, _>("id")?;
+            problems.push(Problem {
+                task_id: Some(id),
+                description: format!("belongs to a missing project: {}", stat.read::<i64, _>("project_id")?),
+                fix: Some(Fix::Sql {
+                    sql: format!("UPDATE tasks SET project_id = {} WHERE id = ?", DEFAULT_PROJECT),
+                    id: Some(id),
+                }),
+            });
+        }
+
+        let sql = "SELECT id, recurrence FROM tasks WHERE recurrence IS NOT NULL;";
+        let mut stat = self.connection.prepare(sql)?;
+        while let State::Row = stat.next()? {
+            let id = stat.read::<i64, _>("id")?;
+            let rule = stat.read::<String, _>("recurrence")?;
+            if let Err(error) = rule.parse::<Recurrence>() {
+                problems.push(Problem {
+                    task_id: Some(id),
+                    description: format!("recurrence {:?} is invalid: {}", rule, error),
+                    fix: Some(Fix::Sql {
+                        sql: String::from("UPDATE tasks SET recurrence = NULL WHERE id = ?"),
+                        id: Some(id),
+                    }),
+                });
+            }
+        }
+
+        let sql = "SELECT id, uuid FROM tasks;";
+        let mut stat = self.connection.prepare(sql)?;
+        while let State::Row = stat.next()? {
+            let id = stat.read::<i64, _>("id")?;
+            let uuid = stat.read::<Option<String>, _>("uuid")?;
+            if uuid.as_deref().and_then(|uuid| Uuid::parse_str(uuid).ok()).is_none() {
+                problems.push(Problem {
+                    task_id: Some(id),
+                    description: format!("uuid is not a UUID: {:?}", uuid.unwrap_or_default()),
+                    fix: Some(Fix::Sql {
+                        sql: format!("UPDATE tasks SET uuid = '{}' WHERE id = ?", Uuid::new_v4()),
+                        id: Some(id),
+                    }),
+                });
+            }
+        }
+
+        let mut stat = self.connection.prepare("PRAGMA foreign_key_check;")?;
+        while let State::Row = stat.next()? {
+            let table = stat.read::<String, _>("table")?;
+            let parent = stat.read::<String, _>("parent")?;
+            // tasks rows are covered by the project and parent checks above
+            if table == "tasks" {
+                continue;
+            }
+            let rowid = stat.read::<Option<i64>, _>("rowid")?;
+            problems.push(Problem {
+                task_id: None,
+                description: format!("{} row {} points to a missing {} row", table, rowid.unwrap_or(0), parent),
+                fix: rowid.map(|rowid| Fix::Sql {
+                    sql: format!("DELETE FROM {} WHERE rowid = ?", table),
+                    id: Some(rowid),
+                }),
+            });
+        }
+
+        let sql = "
+            SELECT COUNT(*) != COUNT(DISTINCT position)
+                OR COALESCE(MIN(position) != 0 OR MAX(position) != COUNT(*) - 1, 0)
+            FROM tasks;
+        ";
+        let mut stat = self.connection.prepare(sql)?;
+        stat.next()?;
+        if stat.read::<i64, _>(0)? == 1 {
+            problems.push(Problem {
+                task_id: None,
+                description: String::from("task positions have duplicates or gaps"),
+                fix: Some(Fix::Sql {
+                    sql: String::from(
+                        "
+                        UPDATE tasks SET position = (
+                            SELECT COUNT(*) FROM tasks AS t
+                            WHERE t.position < tasks.position
+                                OR (t.position = tasks.position AND t.id < tasks.id)
+                        )
+                        ",
+                    ),
+                    id: None,
+                }),
+            });
+        }
+
+        Ok(problems)
+    }
+
+    pub fn repair(&self) -> Result<usize> {
+        let mut fixed = 0;
+        for _ in 0..REPAIR_ROUNDS {
+            let problems = self.diagnose()?;
+            let fixes: Vec<_> = problems.iter().filter_map(|problem| problem.fix.as_ref()).collect();
+            if fixes.is_empty() {
+                break;
+            }
+
+            // the journal mode can't change inside a transaction
+            if fixes.iter().any(|fix| matches!(fix, Fix::Upgrade)) {
+                self.upgrade()?;
+                fixed += 1;
+                continue;
+            }
+            self.transaction(|| {
+                for fix in &fixes {
+                    if let Fix::Sql { sql, id } = fix {
+                        let mut stat = self.connection.prepare(sql)?;
+                        if let Some(id) = id {
+                            stat.bind((1, *id))?;
+                        }
+                        stat.next()?;
+                    }
+                }
+                Ok(())
+            })?;
+            fixed += fixes.len();
+        }
+
+        Ok(fixed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::TaskStore;
+
+    fn descriptions(db: &Db) -> Vec<String> {
+        db.diagnose()
+            .unwrap()
+            .iter()
+            .map(|problem| problem.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_diagnose_and_repair() {
+        let db = Db::open(":memory:").unwrap();
+        for subject in ["a", "b", "c"] {
+            db.insert_one(DEFAULT_PROJECT, subject, "").unwrap();
+        }
+        assert!(descriptions(&db).is_empty());
+
+        db.connection
+            .execute(
+                "
+                PRAGMA foreign_keys = OFF;
+                UPDATE tasks SET due = '2025/13/01', done = 1 WHERE id = 1;
+                UPDATE tasks SET priority = 7, recurrence = 'FREQ=HOURLY', position = 0 WHERE id = 2;
+                UPDATE tasks SET project_id = 9, parent_id = 3, uuid = NULL WHERE id = 3;
+                INSERT INTO dependencies (task_id, blocker_id) VALUES (1, 42);
+                INSERT INTO fields (task_id, name, kind, value) VALUES (2, 'points', 'number', 'lots');
+                PRAGMA foreign_keys = ON;
+                ",
+            )
+            .unwrap();
+        assert_eq!(
+            descriptions(&db),
+            [
+                "task 1: due is not a date: \"2025/13/01\"",
+                "task 1: is done but has no completion time",
+                "task 2: priority is not between 0 and 3: \"7\"",
+                "task 3: has a missing or circular parent: \"3\"",
+                "task 2: has an invalid custom field: \"points=lots\"",
+                "task 3: belongs to a missing project: 9",
+                "task 2: recurrence \"FREQ=HOURLY\" is invalid: unsupported FREQ: \"HOURLY\"",
+                "task 3: uuid is not a UUID: \"\"",
+                "dependencies row 1 points to a missing tasks row",
+                "task positions have duplicates or gaps",
+            ],
+        );
+        assert!(db.diagnose().unwrap().iter().all(Problem::fixable));
+
+        assert_eq!(db.repair().unwrap(), 10);
+        assert!(descriptions(&db).is_empty());
+        let task = db.get_one(1).unwrap().unwrap();
+        assert!(task.due.is_none());
+        assert!(task.completed_at.is_some());
+        assert!(task.blockers.is_empty());
+        assert!(db.get_one(2).unwrap().unwrap().fields.is_empty());
+        let task = db.get_one(3).unwrap().unwrap();
+        assert_eq!((task.project_id, task.parent_id), (DEFAULT_PROJECT, None));
+    }
+
+    #[test]
+    fn test_diagnose_cycles() {
+        let db = Db::open(":memory:").unwrap();
+        for subject in ["a", "b", "c", "d"] {
+            db.insert_one(DEFAULT_PROJECT, subject, "").unwrap();
+        }
+        db.connection
+            .execute(
+                "
+                UPDATE tasks SET parent_id = 2 WHERE id = 1;
+                UPDATE tasks SET parent_id = 1 WHERE id = 2;
+                UPDATE tasks SET parent_id = 1 WHERE id = 3;
+                INSERT INTO dependencies (task_id, blocker_id) VALUES (4, 3), (3, 2), (2, 4), (1, 3);
+                ",
+            )
+            .unwrap();
+        assert_eq!(
+            descriptions(&db),
+            ["task 1: is its own ancestor", "task 2: is blocked by itself through a dependency cycle"],
+        );
+
+        assert_eq!(db.repair().unwrap(), 2);
+        assert!(descriptions(&db).is_empty());
+        assert_eq!(db.get_one(2).unwrap().unwrap().parent_id, Some(1));
+        assert_eq!(db.get_one(1).unwrap().unwrap().blockers, [3]);
+        assert!(db.get_one(2).unwrap().unwrap().blockers.is_empty());
+    }
+
+    #[test]
+    fn test_diagnose_old_schema() {
+        let db = Db::open_unmigrated(":memory:").unwrap();
+        db.connection
+            .execute(
+                "
+                CREATE TABLE tasks (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    done BOOLEAN NOT NULL DEFAULT 0,
+                    subject VARCHAR(50) NOT NULL,
+                    body TEXT NOT NULL DEFAULT '',
+                    created DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    due DATETIME NOT NULL DEFAULT ''
+                );
+                ",
+            )
+            .unwrap();
+        assert_eq!(descriptions(&db), [format!("schema is at version 0, expected {}", SCHEMA_VERSION)]);
+        assert_eq!(db.user_version().unwrap(), 0);
+        assert!(db.diagnose().unwrap()[0].fixable());
+
+        assert_eq!(db.repair().unwrap(), 1);
+        assert!(descriptions(&db).is_empty());
+
+        db.connection.execute("PRAGMA user_version = 999;").unwrap();
+        assert!(!db.diagnose().unwrap()[0].fixable());
+        assert_eq!(db.repair().unwrap(), 0);
+    }
+}
@@ -0,0 +1,76 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum DbError {
+    Sqlite(sqlite::Error),
+    Io(io::Error),
+    SchemaTooNew { version: i64, supported: i64 },
+    InvalidValue { id: i64, column: &'static str, value: String },
+    ProjectExists(String),
+    DefaultProject,
+    ParentCycle { id: i64, parent_id: i64 },
+    DependencyCycle { id: i64, blocker_id: i64 },
+    TaskExists(i64),
+    StaleTask(i64),
+    InvalidBackup(String),
+}
+
+pub type Result<T> = std::result::Result<T, DbError>;
+
+impl fmt::Display for DbError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DbError::Sqlite(error) => write!(f, "database error: {}", error),
+            DbError::Io(error) => write!(f, "{}", error),
+            DbError::SchemaTooNew { version, supported } => write!(
+                f,
+                "database schema version {} is newer than supported version {}",
+                version, supported,
+            ),
+            DbError::InvalidValue { id, column, value } => write!(
+                f,
+                "task {} has an invalid {}: {:?}",
+                id, column, value,
+            ),
+            DbError::ProjectExists(name) => write!(f, "project {:?} already exists", name),
+            DbError::DefaultProject => write!(f, "the default project cannot be deleted"),
+            DbError::ParentCycle { id, parent_id } => write!(
+                f,
+                "task {} cannot be moved under its own subtask {}",
+                id, parent_id,
+            ),
+            DbError::DependencyCycle { id, blocker_id } => write!(
+                f,
+                "task {} cannot be blocked by task {} which depends on it",
+                id, blocker_id,
+            ),
+            DbError::TaskExists(id) => write!(f, "task {} already exists", id),
+            DbError::StaleTask(id) => write!(f, "task {} was changed by another writer", id),
+            DbError::InvalidBackup(reason) => write!(f, "not a valid backup: {}", reason),
+        }
+    }
+}
+
+impl Error for DbError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DbError::Sqlite(error) => Some(error),
+            DbError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<sqlite::Error> for DbError {
+    fn from(error: sqlite::Error) -> Self {
+        DbError::Sqlite(error)
+    }
+}
+
+impl From<io::Error> for DbError {
+    fn from(error: io::Error) -> Self {
+        DbError::Io(error)
+    }
+}
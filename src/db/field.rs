@@ -0,0 +1,187 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use chrono::NaiveDate;
+use sqlite::Value;
+
+use super::{Task, DATE_FORMAT};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FieldValue {
+    Text(String),
+    Number(f64),
+    Date(NaiveDate),
+    Bool(bool),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+    pub name: String,
+    pub value: FieldValue,
+}
+
+impl FieldValue {
+    // quote a value to keep it as text, e.g. "42"
+    pub fn parse(input: &str) -> Self {
+        let input = input.trim();
+        if let Some(text) = input.strip_prefix('"').and_then(|input| input.strip_suffix('"')) {
+            return FieldValue::Text(text.to_string());
+        }
+        match input {
+            "true" => return FieldValue::Bool(true),
+            "false" => return FieldValue::Bool(false),
+            _ => (),
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(input, DATE_FORMAT) {
+            return FieldValue::Date(date);
+        }
+        match input.parse::<f64>() {
+            Ok(number) if number.is_finite() => FieldValue::Number(number),
+            _ => FieldValue::Text(input.to_string()),
+        }
+    }
+
+    pub(super) fn kind(&self) -> &'static str {
+        match self {
+            FieldValue::Text(_) => "text",
+            FieldValue::Number(_) => "number",
+            FieldValue::Date(_) => "date",
+            FieldValue::Bool(_) => "bool",
+        }
+    }
+
+    pub(super) fn to_value(&self) -> Value {
+        match self {
+            FieldValue::Text(text) => Value::String(text.clone()),
+            FieldValue::Number(number) => Value::Float(*number),
+            FieldValue::Date(date) => Value::String(date.format(DATE_FORMAT).to_string()),
+            FieldValue::Bool(value) => Value::Integer((*value).into()),
+        }
+    }
+
+    pub(super) fn from_value(kind: &str, value: Value) -> Option<Self> {
+        match (kind, value) {
+            ("text", Value::String(text)) => Some(FieldValue::Text(text)),
+            ("number", Value::Float(number)) => Some(FieldValue::Number(number)),
+            ("number", Value::Integer(number)) => Some(FieldValue::Number(number as f64)),
+            ("date", Value::String(date)) => {
+                NaiveDate::parse_from_str(&date, DATE_FORMAT).ok().map(FieldValue::Date)
+            }
+            ("bool", Value::Integer(value @ (0 | 1))) => Some(FieldValue::Bool(value == 1)),
+            _ => None,
+        }
+    }
+
+    // the same order SQLite uses for the stored values: numbers before text
+    pub(crate) fn compare(&self, other: &Self) -> Ordering {
+        let number = |value: &Self| match value {
+            FieldValue::Number(number) => Some(*number),
+            FieldValue::Bool(value) => Some(if *value { 1.0 } else { 0.0 }),
+            _ => None,
+        };
+        match (number(self), number(other)) {
+            (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => self.to_string().cmp(&other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FieldValue::Text(text) => write!(f, "{}", text),
+            FieldValue::Number(number) => write!(f, "{}", number),
+            FieldValue::Date(date) => write!(f, "{}", date.format(DATE_FORMAT)),
+            FieldValue::Bool(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl Task {
+    pub fn field(&self, name: &str) -> Option<&FieldValue> {
+        self.fields
+            .iter()
+            .find(|field| field.name.eq_ignore_ascii_case(name))
+            .map(|field| &field.value)
+    }
+
+    pub fn set_field(&mut self, name: &str, value: Option<FieldValue>) {
+        set(&mut self.fields, name, value);
+    }
+}
+
+// fields stay sorted by name, which is unique ignoring ASCII case like in the database
+fn set(fields: &mut Vec<Field>, name: &str, value: Option<FieldValue>) {
+    fields.retain(|field| !field.name.eq_ignore_ascii_case(name));
+    if let Some(value) = value {
+        let key = name.to_ascii_lowercase();
+        let index = fields.partition_point(|field| field.name.to_ascii_lowercase() < key);
+        fields.insert(
+            index,
+            Field {
+                name: name.to_string(),
+                value,
+            },
+        );
+    }
+}
+
+impl Field {
+    pub(crate) fn normalize(fields: &[Field]) -> Vec<Field> {
+        let mut normalized = vec![];
+        for field in fields {
+            set(&mut normalized, &field.name, Some(field.value.clone()));
+        }
+        normalized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(FieldValue::parse("ACME"), FieldValue::Text(String::from("ACME")));
+        assert_eq!(FieldValue::parse(" 3 "), FieldValue::Number(3.0));
+        assert_eq!(FieldValue::parse("-0.5"), FieldValue::Number(-0.5));
+        assert_eq!(FieldValue::parse("\"42\""), FieldValue::Text(String::from("42")));
+        assert_eq!(FieldValue::parse("NaN"), FieldValue::Text(String::from("NaN")));
+        assert_eq!(FieldValue::parse("true"), FieldValue::Bool(true));
+        assert_eq!(
+            FieldValue::parse("2025-03-01"),
+            FieldValue::Date(NaiveDate::from_ymd_opt(2025, 3, 1).unwrap()),
+        );
+        assert_eq!(FieldValue::parse("2.5").to_string(), "2.5");
+        assert_eq!(FieldValue::parse("8").to_string(), "8");
+    }
+
+    #[test]
+    fn test_compare() {
+        let values = ["b", "10", "true", "2", "a", "2025-01-01"].map(FieldValue::parse);
+        let mut sorted = values.to_vec();
+        sorted.sort_by(FieldValue::compare);
+        let sorted: Vec<_> = sorted.iter().map(FieldValue::to_string).collect();
+        assert_eq!(sorted, ["true", "2", "10", "2025-01-01", "a", "b"]);
+    }
+
+    #[test]
+    fn test_normalize() {
+        let field = |name: &str, value: &str| Field {
+            name: name.to_string(),
+            value: FieldValue::parse(value),
+        };
+        let fields = Field::normalize(&[
+            field("points", "3"),
+            field("Customer", "ACME"),
+            field("ticket", "T-1"),
+            field("POINTS", "5"),
+        ]);
+        let names: Vec<_> = fields.iter().map(|field| field.name.as_str()).collect();
+        assert_eq!(names, ["Customer", "POINTS", "ticket"]);
+        assert_eq!(fields[1].value, FieldValue::Number(5.0));
+        assert_eq!(Field::normalize(&fields), fields);
+    }
+}
@@ -0,0 +1,170 @@
+// Each entry upgrades the schema by one version, tracked in `PRAGMA user_version`.
+// Released entries must never change; append a new one instead.
+pub const MIGRATIONS: &[&str] = &[
+    // databases created before versioning already have this table
+    "
+    CREATE TABLE IF NOT EXISTS tasks (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        done BOOLEAN NOT NULL DEFAULT 0,
+        subject VARCHAR(50) NOT NULL,
+        body TEXT NOT NULL DEFAULT '',
+        created DATETIME DEFAULT CURRENT_TIMESTAMP,
+        due DATETIME NOT NULL DEFAULT ''
+    );
+    ",
+    "
+    ALTER TABLE tasks ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+    UPDATE tasks SET position = (SELECT COUNT(*) FROM tasks AS t WHERE t.id < tasks.id);
+    CREATE INDEX tasks_position ON tasks (position);
+    ",
+    // due becomes a nullable ISO date instead of '' or 'YYYY/MM/DD'
+    "
+    CREATE TABLE tasks_new (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        done BOOLEAN NOT NULL DEFAULT 0,
+        subject VARCHAR(50) NOT NULL,
+        body TEXT NOT NULL DEFAULT '',
+        created DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        due DATE,
+        position INTEGER NOT NULL DEFAULT 0
+    );
+    INSERT INTO tasks_new (id, done, subject, body, created, due, position)
+    SELECT id, done, subject, body, COALESCE(created, CURRENT_TIMESTAMP),
+        CASE
+            WHEN date(replace(due, '/', '-')) = replace(due, '/', '-')
+            THEN replace(due, '/', '-')
+        END,
+        position
+    FROM tasks;
+    DELETE FROM sqlite_sequence WHERE name = 'tasks_new';
+    INSERT INTO sqlite_sequence (name, seq)
+    SELECT 'tasks_new', seq FROM sqlite_sequence WHERE name = 'tasks';
+    DROP TABLE tasks;
+    ALTER TABLE tasks_new RENAME TO tasks;
+    CREATE INDEX tasks_position ON tasks (position);
+    ",
+    "
+    ALTER TABLE tasks ADD COLUMN deleted_at DATETIME;
+    CREATE INDEX tasks_deleted_at ON tasks (deleted_at);
+    ",
+    "
+    CREATE VIRTUAL TABLE tasks_fts USING fts5(
+        subject,
+        body,
+        content = 'tasks',
+        content_rowid = 'id'
+    );
+    INSERT INTO tasks_fts (tasks_fts) VALUES ('rebuild');
+    CREATE TRIGGER tasks_fts_insert AFTER INSERT ON tasks BEGIN
+        INSERT INTO tasks_fts (rowid, subject, body)
+        VALUES (new.id, new.subject, new.body);
+    END;
+    CREATE TRIGGER tasks_fts_delete AFTER DELETE ON tasks BEGIN
+        INSERT INTO tasks_fts (tasks_fts, rowid, subject, body)
+        VALUES ('delete', old.id, old.subject, old.body);
+    END;
+    CREATE TRIGGER tasks_fts_update AFTER UPDATE OF subject, body ON tasks BEGIN
+        INSERT INTO tasks_fts (tasks_fts, rowid, subject, body)
+        VALUES ('delete', old.id, old.subject, old.body);
+        INSERT INTO tasks_fts (rowid, subject, body)
+        VALUES (new.id, new.subject, new.body);
+    END;
+    ",
+    "
+    CREATE TABLE tags (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL UNIQUE COLLATE NOCASE
+    );
+    CREATE TABLE task_tags (
+        task_id INTEGER NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+        tag_id INTEGER NOT NULL REFERENCES tags (id) ON DELETE CASCADE,
+        PRIMARY KEY (task_id, tag_id)
+    );
+    CREATE INDEX task_tags_tag_id ON task_tags (tag_id);
+    ",
+    "
+    CREATE TABLE projects (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+        archived BOOLEAN NOT NULL DEFAULT 0,
+        created DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+    INSERT INTO projects (id, name) VALUES (1, 'todo');
+    ALTER TABLE tasks ADD COLUMN project_id INTEGER NOT NULL DEFAULT 1 REFERENCES projects (id);
+    CREATE INDEX tasks_project_id ON tasks (project_id);
+    ",
+    "
+    ALTER TABLE tasks ADD COLUMN parent_id INTEGER REFERENCES tasks (id) ON DELETE SET NULL;
+    CREATE INDEX tasks_parent_id ON tasks (parent_id);
+    ",
+    "
+    CREATE TABLE dependencies (
+        task_id INTEGER NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+        blocker_id INTEGER NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+        PRIMARY KEY (task_id, blocker_id)
+    );
+    CREATE INDEX dependencies_blocker_id ON dependencies (blocker_id);
+    ",
+    "
+    ALTER TABLE tasks ADD COLUMN priority INTEGER NOT NULL DEFAULT 0;
+    ",
+    "
+    ALTER TABLE tasks ADD COLUMN recurrence TEXT;
+    ",
+    "
+    CREATE TABLE time_entries (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        task_id INTEGER NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+        started_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        stopped_at DATETIME
+    );
+    CREATE INDEX time_entries_task_id ON time_entries (task_id);
+    CREATE UNIQUE INDEX time_entries_running ON time_entries ((stopped_at IS NULL))
+    WHERE stopped_at IS NULL;
+    ",
+    "
+    ALTER TABLE tasks ADD COLUMN completed_at DATETIME;
+    ALTER TABLE tasks ADD COLUMN updated_at DATETIME;
+    UPDATE tasks SET updated_at = created;
+    UPDATE tasks SET completed_at = updated_at WHERE done = 1;
+    CREATE INDEX tasks_completed_at ON tasks (completed_at);
+    ",
+    "
+    CREATE TABLE revisions (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        task_id INTEGER NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+        subject TEXT NOT NULL,
+        body TEXT NOT NULL,
+        created DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE INDEX revisions_task_id ON revisions (task_id);
+    ",
+    "
+    ALTER TABLE tasks ADD COLUMN version INTEGER NOT NULL DEFAULT 0;
+    ",
+    "
+    ALTER TABLE tasks ADD COLUMN uuid TEXT;
+    UPDATE tasks SET uuid = lower(
+        hex(randomblob(4)) || '-' || hex(randomblob(2)) || '-4' || substr(hex(randomblob(2)), 2) || '-'
+        || substr('89ab', 1 + abs(random() % 4), 1) || substr(hex(randomblob(2)), 2) || '-'
+        || hex(randomblob(6))
+    );
+    CREATE UNIQUE INDEX tasks_uuid ON tasks (uuid);
+    ",
+    "
+    CREATE TABLE fields (
+        task_id INTEGER NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+        name TEXT NOT NULL COLLATE NOCASE,
+        kind TEXT NOT NULL CHECK (kind IN ('text', 'number', 'date', 'bool')),
+        value NOT NULL,
+        PRIMARY KEY (task_id, name)
+    );
+    CREATE INDEX fields_name ON fields (name, value);
+    ",
+    "
+    ALTER TABLE tasks ADD COLUMN archived_at DATETIME;
+    CREATE INDEX tasks_archived_at ON tasks (archived_at);
+    ",
+];
+
+pub const SCHEMA_VERSION: i64 = MIGRATIONS.len() as i64;
@@ -0,0 +1,302 @@
+use std::cmp::Ordering;
+
+use chrono::{Local, NaiveDate};
+use sqlite::Value;
+use uuid::Uuid;
+
+use super::{Field, FieldValue, Sort, Task, DATE_FORMAT};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TaskQuery {
+    uuid: Option<Uuid>,
+    project_id: Option<i64>,
+    done: Option<bool>,
+    due_before: Option<NaiveDate>,
+    due_after: Option<NaiveDate>,
+    completed_since: Option<NaiveDate>,
+    text: Option<String>,
+    tags: Vec<String>,
+    fields: Vec<Field>,
+    sort: Sort,
+    sort_field: Option<String>,
+    limit: Option<usize>,
+    offset: usize,
+}
+
+impl TaskQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn uuid(mut self, uuid: Uuid) -> Self {
+        self.uuid = Some(uuid);
+        self
+    }
+
+    pub fn project(mut self, project_id: i64) -> Self {
+        self.project_id = Some(project_id);
+        self
+    }
+
+    pub fn done(mut self, done: bool) -> Self {
+        self.done = Some(done);
+        self
+    }
+
+    pub fn due_before(mut self, date: NaiveDate) -> Self {
+        self.due_before = Some(date);
+        self
+    }
+
+    pub fn due_after(mut self, date: NaiveDate) -> Self {
+        self.due_after = Some(date);
+        self
+    }
+
+    pub fn completed_since(mut self, date: NaiveDate) -> Self {
+        self.completed_since = Some(date);
+        self
+    }
+
+    pub fn text(mut self, text: &str) -> Self {
+        self.text = Some(text.to_string());
+        self
+    }
+
+    // tags match case-insensitively, so a repeat would never satisfy the HAVING count
+    pub fn tag(mut self, tag: &str) -> Self {
+        if !self.tags.iter().any(|name| name.eq_ignore_ascii_case(tag)) {
+            self.tags.push(tag.to_string());
+        }
+        self
+    }
+
+    pub fn tags(self, tags: &[String]) -> Self {
+        tags.iter().fold(self, |query, tag| query.tag(tag))
+    }
+
+    pub fn field(mut self, name: &str, value: FieldValue) -> Self {
+        self.fields.push(Field {
+            name: name.to_string(),
+            value,
+        });
+        self
+    }
+
+    pub fn sort(mut self, sort: Sort) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    // tasks without the field come last, ties are ordered by `sort`
+    pub fn sort_field(mut self, name: &str) -> Self {
+        self.sort_field = Some(name.to_string());
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub(super) fn match_text(&self) -> Option<String> {
+        let terms = self
+            .text
+            .as_deref()?
+            .split_whitespace()
+            .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+            .collect::<Vec<_>>();
+        if terms.is_empty() {
+            None
+        } else {
+            Some(terms.join(" "))
+        }
+    }
+
+    pub(super) fn filter(&self, text: bool) -> (String, Vec<Value>) {
+        let mut conditions = vec![
+            String::from("tasks.deleted_at IS NULL"),
+            String::from("tasks.archived_at IS NULL"),
+        ];
+        let mut values = vec![];
+        let date = |date: NaiveDate| Value::String(date.format(DATE_FORMAT).to_string());
+
+        if let Some(uuid) = self.uuid {
+            conditions.push(String::from("tasks.uuid = ?"));
+            values.push(Value::String(uuid.to_string()));
+        }
+        if let Some(project_id) = self.project_id {
+            conditions.push(String::from("tasks.project_id = ?"));
+            values.push(Value::Integer(project_id));
+        }
+        if let Some(done) = self.done {
+            conditions.push(String::from("tasks.done = ?"));
+            values.push(Value::Integer(done.into()));
+        }
+        if let Some(due) = self.due_before {
+            conditions.push(String::from("tasks.due < ?"));
+            values.push(date(due));
+        }
+        if let Some(due) = self.due_after {
+            conditions.push(String::from("tasks.due > ?"));
+            values.push(date(due));
+        }
+        if let Some(since) = self.completed_since {
+            conditions.push(String::from("date(tasks.completed_at, 'localtime') >= ?"));
+            values.push(date(since));
+        }
+        if let Some(text) = self.match_text().filter(|_| text) {
+            conditions.push(String::from(
+                "tasks.id IN (SELECT rowid FROM tasks_fts WHERE tasks_fts MATCH ?)",
+            ));
+            values.push(Value::String(text));
+        }
+        if !self.tags.is_empty() {
+            conditions.push(format!(
+                "
+                tasks.id IN (
+                    SELECT task_tags.task_id FROM task_tags
+                    JOIN tags ON tags.id = task_tags.tag_id
+                    WHERE tags.name IN ({})
+                    GROUP BY task_tags.task_id
+                    HAVING COUNT(*) = ?
+                )
+                ",
+                vec!["?"; self.tags.len()].join(", "),
+            ));
+            values.extend(self.tags.iter().map(|tag| Value::String(tag.clone())));
+            values.push(Value::Integer(self.tags.len() as i64));
+        }
+
+        for field in &self.fields {
+            conditions.push(String::from(
+                "tasks.id IN (SELECT task_id FROM fields WHERE name = ? AND kind = ? AND value = ?)",
+            ));
+            values.push(Value::String(field.name.clone()));
+            values.push(Value::String(field.value.kind().to_string()));
+            values.push(field.value.to_value());
+        }
+
+        (conditions.join(" AND "), values)
+    }
+
+    pub(super) fn order_by(&self) -> (String, Vec<Value>) {
+        match &self.sort_field {
+            Some(name) => {
+                let value = "(SELECT value FROM fields WHERE fields.task_id = tasks.id AND fields.name = ?)";
+                (
+                    format!("{} IS NULL, {}, {}", value, value, self.sort.order_by()),
+                    vec![Value::String(name.clone()); 2],
+                )
+            }
+            None => (self.sort.order_by().to_string(), vec![]),
+        }
+    }
+
+    pub(crate) fn terms(&self) -> Vec<String> {
+        match &self.text {
+            Some(text) => text.split_whitespace().map(str::to_lowercase).collect(),
+            None => vec![],
+        }
+    }
+
+    pub(crate) fn matches(&self, task: &Task) -> bool {
+        let completed = task
+            .completed_at
+            .map(|completed_at| completed_at.with_timezone(&Local).date_naive());
+
+        task.deleted_at.is_none()
+            && task.archived_at.is_none()
+            && self.uuid.is_none_or(|uuid| task.uuid == uuid)
+            && self.project_id.is_none_or(|project_id| task.project_id == project_id)
+            && self.done.is_none_or(|done| task.done == done)
+            && self.due_before.is_none_or(|date| task.due.is_some_and(|due| due < date))
+            && self.due_after.is_none_or(|date| task.due.is_some_and(|due| due > date))
+            && self
+                .completed_since
+                .is_none_or(|date| completed.is_some_and(|completed| completed >= date))
+            && self.tags.iter().all(|tag| {
+                task.tags.iter().any(|name| name.eq_ignore_ascii_case(tag))
+            })
+            && self
+                .fields
+                .iter()
+                .all(|field| task.field(&field.name) == Some(&field.value))
+    }
+
+    pub(crate) fn compare(&self, a: &Task, b: &Task) -> Ordering {
+        let field = match &self.sort_field {
+            Some(name) => match (a.field(name), b.field(name)) {
+                (Some(a), Some(b)) => a.compare(b),
+                (a, b) => a.is_none().cmp(&b.is_none()),
+            },
+            None => Ordering::Equal,
+        };
+        field.then_with(|| self.sort.compare(a, b))
+    }
+
+    pub(crate) fn paginate<T>(&self, items: Vec<T>) -> Vec<T> {
+        let items = items.into_iter().skip(self.offset);
+        match self.limit {
+            Some(limit) => items.take(limit).collect(),
+            None => items.collect(),
+        }
+    }
+
+    pub(super) fn page(&self) -> String {
+        match (self.limit, self.offset) {
+            (None, 0) => String::new(),
+            (limit, offset) => format!(
+                "LIMIT {} OFFSET {}",
+                limit.map_or(-1, |limit| limit as i64),
+                offset,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter() {
+        let query = TaskQuery::new();
+        assert_eq!(
+            query.filter(true),
+            (String::from("tasks.deleted_at IS NULL AND tasks.archived_at IS NULL"), vec![]),
+        );
+        assert_eq!(query.page(), "");
+
+        let query = TaskQuery::new()
+            .project(2)
+            .done(false)
+            .due_before(NaiveDate::from_ymd_opt(2025, 2, 1).unwrap())
+            .text("  ")
+            .limit(10)
+            .offset(20);
+        let (sql, values) = query.filter(true);
+        assert_eq!(
+            sql,
+            "tasks.deleted_at IS NULL AND tasks.archived_at IS NULL \
+             AND tasks.project_id = ? AND tasks.done = ? AND tasks.due < ?",
+        );
+        assert_eq!(
+            values,
+            [Value::Integer(2), Value::Integer(0), Value::String(String::from("2025-02-01"))],
+        );
+        assert_eq!(query.page(), "LIMIT 10 OFFSET 20");
+        assert_eq!(TaskQuery::new().offset(5).page(), "LIMIT -1 OFFSET 5");
+
+        let query = TaskQuery::new().text("say \"hi\"").tag("a").tag("b");
+        assert_eq!(query.match_text().as_deref(), Some("\"say\"* \"\"\"hi\"\"\"*"));
+        let (sql, values) = query.filter(false);
+        assert!(!sql.contains("tasks_fts"));
+        assert_eq!(values.len(), 3);
+    }
+}
@@ -0,0 +1,63 @@
+#[derive(Debug, PartialEq)]
+pub enum Diff<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+pub fn lines<'a>(old: &'a str, new: &'a str) -> Vec<Diff<'a>> {
+    let old: Vec<_> = old.lines().collect();
+    let new: Vec<_> = new.lines().collect();
+
+    // common[i][j] is the longest common subsequence of old[i..] and new[j..]
+    let mut common = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            diff.push(Diff::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && common[i + 1][j] >= common[i][j + 1]) {
+            diff.push(Diff::Removed(old[i]));
+            i += 1;
+        } else {
+            diff.push(Diff::Added(new[j]));
+            j += 1;
+        }
+    }
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines() {
+        assert_eq!(lines("", ""), []);
+        assert_eq!(lines("a\nb", "a\nb"), [Diff::Same("a"), Diff::Same("b")]);
+        assert_eq!(
+            lines("a\nb\nc", "a\nx\nc\nd"),
+            [
+                Diff::Same("a"),
+                Diff::Removed("b"),
+                Diff::Added("x"),
+                Diff::Same("c"),
+                Diff::Added("d"),
+            ],
+        );
+        assert_eq!(lines("a\nb", ""), [Diff::Removed("a"), Diff::Removed("b")]);
+    }
+}
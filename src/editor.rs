@@ -14,6 +14,7 @@ enum EditorStatus {
 pub struct Content {
     pub subject: String,
     pub body: String,
+    pub tags: Vec<String>,
 }
 
 pub struct Editor<'a> {
@@ -34,15 +35,12 @@ impl Editor<'_> {
     }
 
     pub fn handle_key_press_event(&mut self, key_event: KeyEvent) -> bool {
-        match self.status {
-            EditorStatus::Editing => {
-                match key_event.code {
-                    KeyCode::F(1) => self.done(),
-                    _ => drop(self.textarea.input(key_event)),
-                };
-                return true;
-            }
-            _ => (),
+        if let EditorStatus::Editing = self.status {
+            match key_event.code {
+                KeyCode::F(1) => self.done(),
+                _ => drop(self.textarea.input(key_event)),
+            };
+            return true;
         }
 
         false
@@ -52,10 +50,13 @@ impl Editor<'_> {
         self.content.take()
     }
 
-    pub fn start(&mut self, subject: &str, body: &str, done: bool) {
+    pub fn start(&mut self, subject: &str, body: &str, tags: &[String], done: bool) {
         self.done = done;
-        let text = if body == "" {
-            String::from(subject)
+        let subject = tags
+            .iter()
+            .fold(String::from(subject), |subject, tag| format!("{} #{}", subject, tag));
+        let text = if body.is_empty() {
+            subject
         } else {
             format!("{}\n\n{}", subject, body)
         };
@@ -83,11 +84,11 @@ impl Editor<'_> {
                     false
                 }
             })
-            .map(|line| line.clone())
+            .cloned()
             .collect::<Vec<_>>();
 
-        let subject = if let Some(line) = lines.get(0) {
-            line.clone()
+        let (subject, tags) = if let Some(line) = lines.first() {
+            Self::parse_tags(line)
         } else {
             self.content = None;
             self.hide();
@@ -96,19 +97,50 @@ impl Editor<'_> {
 
         let body = if let Some(lines) = lines.get(1..) {
             lines
-                .iter()
-                .map(|line| line.clone())
-                .collect::<Vec<_>>()
+                .to_vec()
                 .join("\n")
-                .clone()
         } else {
             String::from("")
         };
 
-        self.content = Some(Content { subject, body });
+        self.content = Some(Content {
+            subject,
+            body,
+            tags,
+        });
         self.hide();
     }
 
+    fn parse_tags(line: &str) -> (String, Vec<String>) {
+        let mut words = vec![];
+        let mut tags: Vec<String> = vec![];
+        for word in line.split_whitespace() {
+            match Self::parse_tag(word) {
+                Some(tag) => {
+                    if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                        tags.push(tag.to_string());
+                    }
+                }
+                None => words.push(word),
+            }
+        }
+
+        (words.join(" "), tags)
+    }
+
+    pub fn parse_tag(word: &str) -> Option<&str> {
+        let tag = word.strip_prefix('#')?;
+        let mut chars = tag.chars();
+        if !chars.next()?.is_alphabetic() {
+            return None;
+        }
+        if chars.all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '/')) {
+            Some(tag)
+        } else {
+            None
+        }
+    }
+
     fn edit(&mut self) {
         if self.done {
             return;
@@ -143,3 +175,23 @@ impl Widget for &Editor<'_> {
         self.textarea.render(inner, buf);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tags() {
+        let tags = vec![String::from("work")];
+        let mut editor = Editor::new();
+        editor.start("test_subject", "test_body", &tags, false);
+        assert_eq!(editor.textarea.lines()[0], "test_subject #work");
+
+        editor.textarea.insert_str(" #home #1 #Work");
+        editor.handle_key_press_event(KeyCode::F(1).into());
+        let content = editor.get_content().unwrap();
+        assert_eq!(content.subject, "test_subject #1");
+        assert_eq!(content.body, "test_body");
+        assert_eq!(content.tags, ["work", "home"]);
+    }
+}
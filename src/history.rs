@@ -0,0 +1,272 @@
+use crate::db::{DbError, Result, Task};
+use crate::store::TaskStore;
+
+const LIMIT: usize = 100;
+
+pub enum Change {
+    Insert(Task),
+    Update { before: Box<Task>, after: Box<Task> },
+    Trash(Task),
+    Restore(Task),
+    Purge(Task),
+    Archive(Task),
+    Unarchive(Task),
+    Move { id: i64, from: usize, to: usize },
+    Group(Vec<Change>),
+}
+
+// the version undo or redo expects, unless the task only moved, was trashed or archived since
+fn overwrite(store: &dyn TaskStore, expected: &Task, task: &Task) -> Result<Option<Task>> {
+    match store.get_one(task.id)? {
+        Some(current) if current.version != expected.version && edited(&current, expected) => {
+            Err(DbError::StaleTask(task.id))
+        }
+        Some(current) => store.update_one(&Task {
+            version: current.version,
+            ..task.clone()
+        }),
+        None => Ok(None),
+    }
+}
+
+fn edited(current: &Task, expected: &Task) -> bool {
+    let content = |task: &Task| {
+        (
+            task.done,
+            task.subject.clone(),
+            task.body.clone(),
+            task.due,
+            task.tags.clone(),
+            task.fields.clone(),
+            task.project_id,
+            task.parent_id,
+            task.blockers.clone(),
+            task.priority,
+            task.recurrence.clone(),
+        )
+    };
+    content(current) != content(expected)
+}
+
+impl Change {
+    fn revert(&self, store: &dyn TaskStore) -> Result<()> {
+        match self {
+            Change::Insert(task) => drop(store.delete_one(task.id)?),
+            Change::Update { before, after } => drop(overwrite(store, after, before)?),
+            Change::Trash(task) => drop(store.restore_one(task.id)?),
+            Change::Restore(task) => drop(store.trash_one(task.id)?),
+            Change::Purge(task) => drop(store.insert_task(task)?),
+            Change::Archive(task) => drop(store.unarchive_one(task.id)?),
+            Change::Unarchive(task) => drop(store.archive_one(task.id)?),
+            Change::Move { id, from, .. } => drop(store.move_to(*id, *from)?),
+            Change::Group(changes) => {
+                for change in changes.iter().rev() {
+                    change.revert(store)?;
+                }
+            }
+        };
+
+        Ok(())
+    }
+
+    fn apply(&self, store: &dyn TaskStore) -> Result<()> {
+        match self {
+            Change::Insert(task) => drop(store.insert_task(task)?),
+            Change::Update { before, after } => drop(overwrite(store, before, after)?),
+            Change::Trash(task) => drop(store.trash_one(task.id)?),
+            Change::Restore(task) => drop(store.restore_one(task.id)?),
+            Change::Purge(task) => drop(store.purge_one(task.id)?),
+            Change::Archive(task) => drop(store.archive_one(task.id)?),
+            Change::Unarchive(task) => drop(store.unarchive_one(task.id)?),
+            Change::Move { id, to, .. } => drop(store.move_to(*id, *to)?),
+            Change::Group(changes) => {
+                for change in changes {
+                    change.apply(store)?;
+                }
+            }
+        };
+
+        Ok(())
+    }
+}
+
+pub struct History {
+    undo: Vec<Change>,
+    redo: Vec<Change>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self {
+            undo: vec![],
+            redo: vec![],
+        }
+    }
+
+    pub fn record(&mut self, change: Change) {
+        if self.undo.len() >= LIMIT {
+            self.undo.remove(0);
+        }
+        self.undo.push(change);
+        self.redo.clear();
+    }
+
+    pub fn undo(&mut self, store: &dyn TaskStore) -> Result<bool> {
+        let change = match self.undo.pop() {
+            Some(change) => change,
+            None => return Ok(false),
+        };
+
+        match change.revert(store) {
+            Ok(()) => {
+                self.redo.push(change);
+                Ok(true)
+            }
+            Err(error) => {
+                self.undo.push(change);
+                Err(error)
+            }
+        }
+    }
+
+    pub fn redo(&mut self, store: &dyn TaskStore) -> Result<bool> {
+        let change = match self.redo.pop() {
+            Some(change) => change,
+            None => return Ok(false),
+        };
+
+        match change.apply(store) {
+            Ok(()) => {
+                self.undo.push(change);
+                Ok(true)
+            }
+            Err(error) => {
+                self.redo.push(change);
+                Err(error)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{Db, TaskQuery, DEFAULT_PROJECT};
+
+    fn subjects(db: &Db) -> Vec<String> {
+        db.query(&TaskQuery::new().project(DEFAULT_PROJECT))
+            .unwrap()
+            .into_iter()
+            .map(|task| task.subject)
+            .collect()
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let db = Db::open(":memory:").unwrap();
+        let mut history = History::new();
+
+        let a = db.insert_one(DEFAULT_PROJECT, "a", "").unwrap().unwrap();
+        history.record(Change::Insert(a.clone()));
+        let b = db.insert_one(DEFAULT_PROJECT, "b", "").unwrap().unwrap();
+        history.record(Change::Insert(b.clone()));
+
+        let mut after = a.clone();
+        after.subject = String::from("edited");
+        let after = db.update_one(&after).unwrap().unwrap();
+        history.record(Change::Update {
+            before: Box::new(a.clone()),
+            after: Box::new(after),
+        });
+
+        let (from, to) = db.move_to(b.id, 0).unwrap().unwrap();
+        history.record(Change::Move { id: b.id, from, to });
+
+        let trashed = db.trash_one(a.id).unwrap().unwrap();
+        history.record(Change::Trash(trashed));
+        assert_eq!(subjects(&db), ["b"]);
+
+        assert!(history.undo(&db).unwrap());
+        assert_eq!(subjects(&db), ["b", "edited"]);
+        assert!(history.undo(&db).unwrap());
+        assert_eq!(subjects(&db), ["edited", "b"]);
+        assert!(history.undo(&db).unwrap());
+        assert_eq!(subjects(&db), ["a", "b"]);
+        assert!(history.undo(&db).unwrap());
+        assert!(history.undo(&db).unwrap());
+        assert!(subjects(&db).is_empty());
+        assert!(!history.undo(&db).unwrap());
+
+        for _ in 0..5 {
+            assert!(history.redo(&db).unwrap());
+        }
+        assert!(!history.redo(&db).unwrap());
+        assert_eq!(subjects(&db), ["b"]);
+        assert_eq!(db.list_trash().unwrap()[0].subject, "edited");
+    }
+
+    #[test]
+    fn test_undo_stale_update() {
+        let db = Db::open(":memory:").unwrap();
+        let mut history = History::new();
+
+        let a = db.insert_one(DEFAULT_PROJECT, "a", "").unwrap().unwrap();
+        let mut after = a.clone();
+        after.subject = String::from("mine");
+        let after = db.update_one(&after).unwrap().unwrap();
+        history.record(Change::Update {
+            before: Box::new(a.clone()),
+            after: Box::new(after.clone()),
+        });
+
+        let mut theirs = after.clone();
+        theirs.subject = String::from("theirs");
+        db.update_one(&theirs).unwrap().unwrap();
+        assert!(matches!(history.undo(&db), Err(DbError::StaleTask(_))));
+        assert_eq!(subjects(&db), ["theirs"]);
+
+        db.update_one(&Task {
+            version: after.version + 1,
+            ..after
+        })
+        .unwrap()
+        .unwrap();
+        assert!(history.undo(&db).unwrap());
+        assert_eq!(subjects(&db), ["a"]);
+        assert!(history.redo(&db).unwrap());
+        assert_eq!(subjects(&db), ["mine"]);
+    }
+
+    #[test]
+    fn test_undo_archive() {
+        let db = Db::open(":memory:").unwrap();
+        let mut history = History::new();
+
+        let mut task = db.insert_one(DEFAULT_PROJECT, "a", "").unwrap().unwrap();
+        task.done = true;
+        db.update_one(&task).unwrap().unwrap();
+        let archived = db.archive_done(Some(DEFAULT_PROJECT), 0).unwrap();
+        history.record(Change::Group(archived.into_iter().map(Change::Archive).collect()));
+        assert!(subjects(&db).is_empty());
+
+        assert!(history.undo(&db).unwrap());
+        assert_eq!(subjects(&db), ["a"]);
+        assert!(history.redo(&db).unwrap());
+        assert_eq!(db.list_archive(DEFAULT_PROJECT).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_record_clears_redo() {
+        let db = Db::open(":memory:").unwrap();
+        let mut history = History::new();
+
+        let a = db.insert_one(DEFAULT_PROJECT, "a", "").unwrap().unwrap();
+        history.record(Change::Insert(a));
+        assert!(history.undo(&db).unwrap());
+
+        let b = db.insert_one(DEFAULT_PROJECT, "b", "").unwrap().unwrap();
+        history.record(Change::Insert(b));
+        assert!(!history.redo(&db).unwrap());
+        assert_eq!(subjects(&db), ["b"]);
+    }
+}
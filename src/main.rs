@@ -1,19 +1,166 @@
+use std::fs;
 use std::io;
+use std::path::Path;
+use std::process;
 
+mod config;
+use config::{Command, Config, USAGE};
 mod date_picker;
+mod diff;
 mod todo;
 use todo::Todo;
 mod db;
+use db::{Db, TaskQuery};
 mod editor;
+mod history;
 mod preview;
+mod project_picker;
+mod prompt;
+mod recurrence;
+mod report;
+mod revision_view;
+mod store;
+use store::{MemoryStore, TaskStore};
 
 fn main() -> io::Result<()> {
+    let config = match Config::from_env() {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("rustodo: {}\n{}", error, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let store: Box<dyn TaskStore> = if config.ephemeral {
+        Box::new(MemoryStore::new())
+    } else {
+        // backup, restore and doctor run on the file as it is, and so does the startup backup
+        let db = open(&config.db, false);
+        let output = match &config.command {
+            Command::Backup(path) => Some(
+                db.backup(path)
+                    .map(|()| format!("backed up to {}\n", path.display()))
+                    .map_err(|error| error.to_string()),
+            ),
+            Command::Restore(path) => Some(
+                db.restore(path)
+                    .map(|()| format!("restored from {}\n", path.display()))
+                    .map_err(|error| error.to_string()),
+            ),
+            Command::Doctor { fix } => Some(doctor(&db, *fix)),
+            Command::Run => {
+                let dir = config.db.parent().unwrap_or(Path::new("")).join("backups");
+                db.rotate_backups(dir, config.backups)
+                    .err()
+                    .map(|error| Err(format!("cannot back up: {}", error)))
+            }
+            _ => None,
+        };
+        if let Some(output) = output {
+            return finish(output);
+        }
+        drop(db);
+        Box::new(open(&config.db, true))
+    };
+
+    let output = match config.command {
+        Command::Report => Some(
+            store.time_report()
+                .map(|time_report| report::render(&time_report))
+                .map_err(|error| error.to_string()),
+        ),
+        Command::List { query, project } => Some(list(store.as_ref(), query, project)),
+        _ => None,
+    };
+    if let Some(output) = output {
+        return finish(output);
+    }
+
+    if let Err(error) = store.purge_trash(config.trash_days) {
+        eprintln!("rustodo: cannot purge trash: {}", error);
+        process::exit(1);
+    }
+    if let Some(days) = config.archive_days {
+        if let Err(error) = store.archive_done(None, days) {
+            eprintln!("rustodo: cannot archive completed tasks: {}", error);
+            process::exit(1);
+        }
+    }
+
     let mut terminal = ratatui::init();
     terminal.clear()?;
 
-    let mut todo = Todo::new();
+    let mut todo = Todo::new(store);
     let result = todo.run(terminal);
 
     ratatui::restore();
     result
 }
+
+fn open(path: &Path, migrate: bool) -> Db {
+    if let Some(parent) = path.parent() {
+        if let Err(error) = fs::create_dir_all(parent) {
+            eprintln!("rustodo: cannot create {}: {}", parent.display(), error);
+            process::exit(1);
+        }
+    }
+
+    let result = if migrate { Db::open(path) } else { Db::open_unmigrated(path) };
+    match result {
+        Ok(db) => db,
+        Err(error) => {
+            eprintln!("rustodo: {}", error);
+            process::exit(1);
+        }
+    }
+}
+
+fn finish(output: Result<String, String>) -> io::Result<()> {
+    match output {
+        Ok(output) => print!("{}", output),
+        Err(error) => {
+            eprintln!("rustodo: {}", error);
+            process::exit(1);
+        }
+    }
+
+    Ok(())
+}
+
+fn doctor(db: &Db, fix: bool) -> Result<String, String> {
+    let problems = db.diagnose().map_err(|error| error.to_string())?;
+    let mut output = String::new();
+    for problem in &problems {
+        let note = if problem.fixable() { "" } else { " (cannot be fixed automatically)" };
+        output.push_str(&format!("{}{}\n", problem, note));
+    }
+
+    if problems.is_empty() {
+        output.push_str("no problems found\n");
+    } else if fix {
+        let fixed = db.repair().map_err(|error| error.to_string())?;
+        let remaining = db.diagnose().map_err(|error| error.to_string())?.len();
+        output.push_str(&format!("applied {} fixes, {} problems remain\n", fixed, remaining));
+    } else if problems.iter().any(|problem| problem.fixable()) {
+        output.push_str("run `rustodo doctor --fix` to repair them\n");
+    }
+
+    Ok(output)
+}
+
+fn list(store: &dyn TaskStore, query: TaskQuery, project: Option<String>) -> Result<String, String> {
+    let query = match project {
+        Some(name) => {
+            let projects = store.list_projects().map_err(|error| error.to_string())?;
+            let project = projects
+                .iter()
+                .find(|project| project.name.eq_ignore_ascii_case(&name))
+                .ok_or(format!("no project named {:?}", name))?;
+            query.project(project.id)
+        }
+        None => query,
+    };
+    let tasks = store.query(&query).map_err(|error| error.to_string())?;
+
+    Ok(report::render_tasks(&tasks))
+}
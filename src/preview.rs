@@ -1,13 +1,19 @@
 use ratatui::{
     buffer::Buffer,
     layout::{Direction, Rect},
+    style::Stylize,
     symbols,
+    text::{Line, Span, Text},
     widgets::{Block, Borders, Clear, Paragraph, Widget},
 };
 
+use crate::db::{MATCH_END, MATCH_START};
+
 pub struct Preview {
     subject: String,
     body: String,
+    details: Vec<String>,
+    time: Option<String>,
     direction: Direction,
 }
 
@@ -16,23 +22,61 @@ impl Preview {
         Self {
             subject: String::new(),
             body: String::new(),
+            details: vec![],
+            time: None,
             direction: Direction::Horizontal,
         }
     }
 
-    pub fn show(&mut self, subject: &String, body: &String) {
-        self.subject = subject.clone();
-        self.body = body.clone();
+    pub fn show(&mut self, subject: &str, body: &str) {
+        self.subject = subject.to_string();
+        self.body = body.to_string();
+    }
+
+    pub fn show_details(&mut self, details: Vec<String>) {
+        self.details = details;
+    }
+
+    pub fn show_time(&mut self, time: Option<String>) {
+        self.time = time;
     }
 
     pub fn set_direction(&mut self, direction: Direction) {
         self.direction = direction;
     }
+
+    fn highlight(text: &str) -> Text<'_> {
+        let mut matching = false;
+        let lines: Vec<_> = text
+            .lines()
+            .map(|line| {
+                let mut spans = vec![];
+                for (i, part) in line.split([MATCH_START, MATCH_END]).enumerate() {
+                    if i > 0 {
+                        matching = !matching;
+                    }
+                    if part.is_empty() {
+                        continue;
+                    }
+                    let span = Span::raw(part);
+                    spans.push(if matching { span.black().on_yellow() } else { span });
+                }
+                Line::from(spans)
+            })
+            .collect();
+
+        Text::from(lines)
+    }
 }
 
 impl Widget for &Preview {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let text = self.subject.clone() + "\n\n" + &self.body;
+        let mut text = Preview::highlight(&self.subject);
+        for detail in &self.details {
+            text.push_line(Line::from(detail.as_str()).dark_gray());
+        }
+        text.push_line("");
+        text.extend(Preview::highlight(&self.body));
 
         let corners = match self.direction {
             Direction::Horizontal => {
@@ -50,10 +94,74 @@ impl Widget for &Preview {
                 }
             }
         };
-        let block = Block::new()
+        let mut block = Block::new()
             .border_set(corners)
             .borders(Borders::ALL);
+        if let Some(time) = &self.time {
+            block = block.title_bottom(Line::from(format!(" {} ", time)).right_aligned());
+        }
         Clear.render(area, buf);
         Paragraph::new(text).block(block).render(area, buf);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_highlight() {
+        let mut preview = Preview::new();
+        preview.show("\u{2}test\u{3}_subject", "a \u{2}test\u{3}");
+        let mut buf = Buffer::empty(Rect::new(0, 0, 16, 6));
+        preview.render(buf.area, &mut buf);
+
+        let expected = Buffer::with_lines(vec![
+            vec!["┬──────────────┐".into()],
+            vec!["│".into(), "test".black().on_yellow(), "_subject  │".into()],
+            vec!["│              │".into()],
+            vec!["│a ".into(), "test".black().on_yellow(), "        │".into()],
+            vec!["│              │".into()],
+            vec!["┴──────────────┘".into()],
+        ]);
+
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn test_render_time() {
+        let mut preview = Preview::new();
+        preview.show("subject", "");
+        preview.show_time(Some(String::from("0:05:00")));
+        let mut buf = Buffer::empty(Rect::new(0, 0, 16, 3));
+        preview.render(buf.area, &mut buf);
+
+        let expected = Buffer::with_lines(vec![
+            "┬──────────────┐",
+            "│subject       │",
+            "┴───── 0:05:00 ┘",
+        ]);
+
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn test_render_details() {
+        let mut preview = Preview::new();
+        preview.show("subject", "body");
+        preview.show_details(vec![String::from("created 2025/01/01")]);
+        let mut buf = Buffer::empty(Rect::new(0, 0, 22, 6));
+        preview.render(buf.area, &mut buf);
+
+        let expected = Buffer::with_lines(vec![
+            Line::from("┬────────────────────┐"),
+            Line::from("│subject             │"),
+            Line::from(vec!["│".into(), "created 2025/01/01".dark_gray(), "  │".into()]),
+            Line::from("│                    │"),
+            Line::from("│body                │"),
+            Line::from("┴────────────────────┘"),
+        ]);
+
+        assert_eq!(buf, expected);
+    }
+}
@@ -0,0 +1,198 @@
+use ratatui::{
+    buffer::Buffer,
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::{Constraint, Direction, Layout, Rect},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph, Widget},
+};
+
+use crate::db::Project;
+
+enum ProjectPickerStatus {
+    Hiding,
+    Picking,
+}
+
+pub enum ProjectAction {
+    Select(i64),
+    Create,
+    Rename(i64),
+    Archive(i64),
+    Delete(i64),
+}
+
+pub struct ProjectPicker {
+    status: ProjectPickerStatus,
+    title: String,
+    projects: Vec<Project>,
+    current: usize,
+    manage: bool,
+    action: Option<ProjectAction>,
+}
+
+impl ProjectPicker {
+    pub fn new() -> Self {
+        Self {
+            status: ProjectPickerStatus::Hiding,
+            title: String::new(),
+            projects: vec![],
+            current: 0,
+            manage: false,
+            action: None,
+        }
+    }
+
+    pub fn handle_key_press_event(&mut self, key_event: KeyEvent) -> bool {
+        if let ProjectPickerStatus::Hiding = self.status {
+            return false;
+        }
+
+        match key_event.code {
+            KeyCode::Char('k') => {
+                self.current = self.current.saturating_sub(1);
+            }
+            KeyCode::Char('j') if self.current + 1 < self.projects.len() => {
+                self.current += 1;
+            }
+            KeyCode::Enter => self.done(ProjectAction::Select),
+            KeyCode::Char('n') if self.manage => {
+                self.action = Some(ProjectAction::Create);
+                self.hide();
+            }
+            KeyCode::Char('r') if self.manage => self.done(ProjectAction::Rename),
+            KeyCode::Char('a') if self.manage => self.done(ProjectAction::Archive),
+            KeyCode::Char('D') if self.manage => self.done(ProjectAction::Delete),
+            KeyCode::Esc => self.hide(),
+            _ => (),
+        };
+
+        true
+    }
+
+    pub fn get_action(&mut self) -> Option<ProjectAction> {
+        self.action.take()
+    }
+
+    pub fn start(&mut self, title: &str, projects: Vec<Project>, current: i64, manage: bool) {
+        self.title = format!(" {} ", title);
+        self.current = projects
+            .iter()
+            .position(|project| project.id == current)
+            .unwrap_or(0);
+        self.projects = projects;
+        self.manage = manage;
+        self.action = None;
+
+        self.status = ProjectPickerStatus::Picking;
+    }
+
+    fn done<F>(&mut self, action: F)
+    where
+        F: FnOnce(i64) -> ProjectAction,
+    {
+        if let Some(project) = self.projects.get(self.current) {
+            self.action = Some(action(project.id));
+        }
+        self.hide();
+    }
+
+    fn hide(&mut self) {
+        self.status = ProjectPickerStatus::Hiding;
+    }
+}
+
+impl Widget for &ProjectPicker {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if let ProjectPickerStatus::Hiding = self.status {
+            return;
+        }
+
+        let layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(30),
+                Constraint::Percentage(40),
+                Constraint::Percentage(30),
+            ])
+            .split(area);
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(0),
+                Constraint::Length(self.projects.len() as u16 + 2),
+                Constraint::Min(0),
+            ])
+            .split(layout[1]);
+        let area = layout[1];
+
+        Clear.render(area, buf);
+        let lines: Vec<_> = self
+            .projects
+            .iter()
+            .enumerate()
+            .map(|(i, project)| {
+                let mut name = project.name.clone();
+                if project.archived {
+                    name += " (archived)";
+                }
+                let width = area.width.saturating_sub(2) as usize;
+                if i == self.current {
+                    Line::from(format!("{:<width$}", name).white().on_red())
+                } else {
+                    Line::from(name)
+                }
+            })
+            .collect();
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .title(self.title.as_str());
+        Paragraph::new(lines).block(block).render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn projects() -> Vec<Project> {
+        ["todo", "work", "home"]
+            .into_iter()
+            .enumerate()
+            .map(|(i, name)| Project {
+                id: i as i64 + 1,
+                name: String::from(name),
+                archived: name == "home",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_project_picker() {
+        let mut picker = ProjectPicker::new();
+        assert!(!picker.handle_key_press_event(KeyCode::Char('j').into()));
+
+        picker.start("projects", projects(), 2, true);
+        let mut buf = Buffer::empty(Rect::new(0, 0, 30, 5));
+        picker.render(buf.area, &mut buf);
+        let expected = Buffer::with_lines(vec![
+            Line::from("         ┌ projects ┐         "),
+            Line::from("         │todo      │         "),
+            Line::from(vec!["         │".into(), "work      ".white().on_red(), "│         ".into()]),
+            Line::from("         │home (arch│         "),
+            Line::from("         └──────────┘         "),
+        ]);
+        assert_eq!(buf, expected);
+
+        assert!(picker.handle_key_press_event(KeyCode::Char('j').into()));
+        assert!(picker.handle_key_press_event(KeyCode::Char('a').into()));
+        assert!(matches!(picker.get_action(), Some(ProjectAction::Archive(3))));
+        assert!(!picker.handle_key_press_event(KeyCode::Enter.into()));
+
+        picker.start("move to", projects(), 1, false);
+        assert!(picker.handle_key_press_event(KeyCode::Char('D').into()));
+        assert!(picker.get_action().is_none());
+        assert!(picker.handle_key_press_event(KeyCode::Enter.into()));
+        assert!(matches!(picker.get_action(), Some(ProjectAction::Select(1))));
+    }
+}
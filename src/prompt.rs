@@ -0,0 +1,120 @@
+use ratatui::{
+    buffer::Buffer,
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::{Constraint, Direction, Layout, Rect},
+    widgets::{Block, Borders, Clear, Widget},
+};
+use tui_textarea::TextArea;
+
+enum PromptStatus {
+    Hiding,
+    Editing,
+}
+
+pub struct Prompt<'a> {
+    status: PromptStatus,
+    title: String,
+    textarea: TextArea<'a>,
+    input: Option<String>,
+}
+
+impl Prompt<'_> {
+    pub fn new() -> Self {
+        Self {
+            status: PromptStatus::Hiding,
+            title: String::new(),
+            textarea: TextArea::default(),
+            input: None,
+        }
+    }
+
+    pub fn handle_key_press_event(&mut self, key_event: KeyEvent) -> bool {
+        if let PromptStatus::Editing = self.status {
+            match key_event.code {
+                KeyCode::Enter => self.done(),
+                KeyCode::Esc => self.hide(),
+                _ => drop(self.textarea.input(key_event)),
+            };
+            return true;
+        }
+
+        false
+    }
+
+    pub fn get_input(&mut self) -> Option<String> {
+        self.input.take()
+    }
+
+    pub fn start(&mut self, title: &str, text: &str) {
+        self.title = format!(" {} ", title);
+        self.textarea = TextArea::default();
+        self.textarea.insert_str(text);
+        self.input = None;
+
+        self.status = PromptStatus::Editing;
+    }
+
+    fn done(&mut self) {
+        self.input = self.textarea.lines().first().cloned();
+        self.hide();
+    }
+
+    fn hide(&mut self) {
+        self.status = PromptStatus::Hiding;
+    }
+}
+
+impl Widget for &Prompt<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if let PromptStatus::Hiding = self.status {
+            return;
+        }
+
+        let layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(20),
+                Constraint::Percentage(60),
+                Constraint::Percentage(20),
+            ])
+            .split(area);
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(0),
+                Constraint::Length(3),
+                Constraint::Min(0),
+            ])
+            .split(layout[1]);
+        let area = layout[1];
+
+        Clear.render(area, buf);
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .title(self.title.as_str());
+        let inner = block.inner(area);
+        block.render(area, buf);
+        self.textarea.render(inner, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompt() {
+        let mut prompt = Prompt::new();
+        assert!(!prompt.handle_key_press_event(KeyCode::Char('a').into()));
+
+        prompt.start("search", "fo");
+        assert!(prompt.handle_key_press_event(KeyCode::Char('o').into()));
+        assert!(prompt.handle_key_press_event(KeyCode::Enter.into()));
+        assert_eq!(prompt.get_input().as_deref(), Some("foo"));
+        assert!(!prompt.handle_key_press_event(KeyCode::Enter.into()));
+
+        prompt.start("search", "bar");
+        assert!(prompt.handle_key_press_event(KeyCode::Esc.into()));
+        assert_eq!(prompt.get_input(), None);
+    }
+}
@@ -0,0 +1,293 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    pub interval: u32,
+    pub weekdays: Vec<Weekday>,
+    pub month_day: Option<u32>,
+    pub after_completion: bool,
+}
+
+const WEEKDAYS: [(&str, Weekday); 7] = [
+    ("MO", Weekday::Mon),
+    ("TU", Weekday::Tue),
+    ("WE", Weekday::Wed),
+    ("TH", Weekday::Thu),
+    ("FR", Weekday::Fri),
+    ("SA", Weekday::Sat),
+    ("SU", Weekday::Sun),
+];
+
+impl Recurrence {
+    pub fn next(&self, due: Option<NaiveDate>, completed: NaiveDate) -> Option<NaiveDate> {
+        let start = match due {
+            Some(due) if !self.after_completion => due,
+            _ => completed,
+        };
+        let mut date = start;
+        let mut step = 0;
+        loop {
+            step += 1;
+            date = self.advance(start, date, step)?;
+            if date > completed || self.after_completion {
+                return Some(date);
+            }
+        }
+    }
+
+    // keeps the day of a monthly or yearly rule that the next due date has to clamp
+    pub fn anchored(&self, due: Option<NaiveDate>) -> Self {
+        let mut recurrence = self.clone();
+        if let Some(due) = due.filter(|due| due.day() > 28) {
+            let by_month = matches!(self.frequency, Frequency::Monthly | Frequency::Yearly);
+            if by_month && !self.after_completion && self.month_day.is_none() {
+                recurrence.month_day = Some(due.day());
+            }
+        }
+        recurrence
+    }
+
+    // months and years count from the start so a clamped day doesn't stick
+    fn advance(&self, start: NaiveDate, date: NaiveDate, step: u32) -> Option<NaiveDate> {
+        let interval = self.interval.max(1);
+        match self.frequency {
+            Frequency::Daily => date.checked_add_days(Days::new(interval.into())),
+            Frequency::Weekly if self.weekdays.is_empty() => {
+                date.checked_add_days(Days::new(7 * u64::from(interval)))
+            }
+            Frequency::Weekly => {
+                let week = date.week(Weekday::Mon).first_day();
+                (1..=7 * u64::from(interval)).find_map(|days| {
+                    let next = date.checked_add_days(Days::new(days))?;
+                    let weeks = (next.week(Weekday::Mon).first_day() - week).num_weeks();
+                    let matches = weeks % i64::from(interval) == 0
+                        && self.weekdays.contains(&next.weekday());
+                    matches.then_some(next)
+                })
+            }
+            Frequency::Monthly => self.add_months(start, step.checked_mul(interval)?),
+            Frequency::Yearly => self.add_months(start, step.checked_mul(12 * interval)?),
+        }
+    }
+
+    // a day past the end of the month falls on its last day
+    fn add_months(&self, start: NaiveDate, months: u32) -> Option<NaiveDate> {
+        let day = self.month_day.unwrap_or(start.day());
+        let first = start.with_day(1)?.checked_add_months(Months::new(months))?;
+        let last = first.checked_add_months(Months::new(1))?.pred_opt()?;
+        first.with_day(day.min(last.day()))
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = String;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let mut frequency = None;
+        let mut interval = 1;
+        let mut weekdays = vec![];
+        let mut month_day = None;
+        let mut after_completion = false;
+
+        let rule = rule.trim().to_uppercase();
+        let rule = rule.strip_prefix("RRULE:").unwrap_or(&rule);
+        for part in rule.split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("expected KEY=VALUE, got {:?}", part))?;
+            match key {
+                "FREQ" => {
+                    frequency = Some(match value {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err(format!("unsupported FREQ: {:?}", value)),
+                    })
+                }
+                "INTERVAL" => {
+                    interval = value
+                        .parse()
+                        .ok()
+                        .filter(|interval| *interval > 0)
+                        .ok_or_else(|| format!("INTERVAL must be a positive number, got {:?}", value))?
+                }
+                "BYDAY" => {
+                    for day in value.split(',') {
+                        let weekday = WEEKDAYS
+                            .iter()
+                            .find(|(name, _)| *name == day)
+                            .map(|(_, weekday)| *weekday)
+                            .ok_or_else(|| format!("unknown BYDAY: {:?}", day))?;
+                        if !weekdays.contains(&weekday) {
+                            weekdays.push(weekday);
+                        }
+                    }
+                }
+                "BYMONTHDAY" => {
+                    month_day = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|day| (1..=31).contains(day))
+                            .ok_or_else(|| format!("BYMONTHDAY must be between 1 and 31, got {:?}", value))?,
+                    )
+                }
+                "FROM" => {
+                    after_completion = match value {
+                        "DUE" => false,
+                        "COMPLETION" => true,
+                        _ => return Err(format!("FROM must be DUE or COMPLETION, got {:?}", value)),
+                    }
+                }
+                _ => return Err(format!("unsupported rule part: {:?}", key)),
+            }
+        }
+
+        let frequency = frequency.ok_or("FREQ is required")?;
+        if !weekdays.is_empty() && frequency != Frequency::Weekly {
+            return Err(String::from("BYDAY is only supported with FREQ=WEEKLY"));
+        }
+        if month_day.is_some() && !matches!(frequency, Frequency::Monthly | Frequency::Yearly) {
+            return Err(String::from("BYMONTHDAY is only supported with FREQ=MONTHLY or FREQ=YEARLY"));
+        }
+        weekdays.sort_by_key(|weekday| weekday.num_days_from_monday());
+
+        Ok(Self {
+            frequency,
+            interval,
+            weekdays,
+            month_day,
+            after_completion,
+        })
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let frequency = match self.frequency {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        };
+        write!(f, "FREQ={}", frequency)?;
+        if self.interval > 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.weekdays.is_empty() {
+            let days: Vec<_> = self
+                .weekdays
+                .iter()
+                .filter_map(|weekday| WEEKDAYS.iter().find(|(_, day)| day == weekday))
+                .map(|(name, _)| *name)
+                .collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if let Some(day) = self.month_day {
+            write!(f, ";BYMONTHDAY={}", day)?;
+        }
+        if self.after_completion {
+            write!(f, ";FROM=COMPLETION")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn next(rule: &str, due: Option<NaiveDate>, completed: NaiveDate) -> Option<NaiveDate> {
+        rule.parse::<Recurrence>().unwrap().next(due, completed)
+    }
+
+    #[test]
+    fn test_parse() {
+        let recurrence: Recurrence = "rrule:freq=weekly;interval=2;byday=fr,mo".parse().unwrap();
+        assert_eq!(recurrence.frequency, Frequency::Weekly);
+        assert_eq!(recurrence.interval, 2);
+        assert_eq!(recurrence.weekdays, [Weekday::Mon, Weekday::Fri]);
+        assert_eq!(recurrence.to_string(), "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,FR");
+        assert_eq!(
+            "FREQ=DAILY;INTERVAL=3;FROM=COMPLETION".parse::<Recurrence>().unwrap().to_string(),
+            "FREQ=DAILY;INTERVAL=3;FROM=COMPLETION",
+        );
+        assert_eq!(
+            "FREQ=MONTHLY;BYMONTHDAY=31".parse::<Recurrence>().unwrap().month_day,
+            Some(31),
+        );
+
+        assert!("INTERVAL=2".parse::<Recurrence>().is_err());
+        assert!("FREQ=HOURLY".parse::<Recurrence>().is_err());
+        assert!("FREQ=DAILY;INTERVAL=0".parse::<Recurrence>().is_err());
+        assert!("FREQ=MONTHLY;BYDAY=MO".parse::<Recurrence>().is_err());
+        assert!("FREQ=WEEKLY;BYDAY=XX".parse::<Recurrence>().is_err());
+        assert!("FREQ=WEEKLY;BYMONTHDAY=31".parse::<Recurrence>().is_err());
+        assert!("FREQ=MONTHLY;BYMONTHDAY=32".parse::<Recurrence>().is_err());
+    }
+
+    #[test]
+    fn test_next() {
+        let today = date(2025, 1, 15);
+        assert_eq!(next("FREQ=DAILY", Some(today), today), Some(date(2025, 1, 16)));
+        assert_eq!(next("FREQ=WEEKLY", Some(today), today), Some(date(2025, 1, 22)));
+        assert_eq!(next("FREQ=MONTHLY", Some(date(2025, 1, 31)), today), Some(date(2025, 2, 28)));
+        assert_eq!(next("FREQ=MONTHLY", Some(date(2025, 1, 31)), date(2025, 4, 10)), Some(date(2025, 4, 30)));
+        assert_eq!(
+            next("FREQ=YEARLY", Some(date(2024, 2, 29)), date(2027, 6, 1)),
+            Some(date(2028, 2, 29)),
+        );
+        assert_eq!(next("FREQ=YEARLY;INTERVAL=2", Some(today), today), Some(date(2027, 1, 15)));
+
+        // 2025-01-15 is a wednesday
+        assert_eq!(next("FREQ=WEEKLY;BYDAY=MO,FR", Some(today), today), Some(date(2025, 1, 17)));
+        assert_eq!(next("FREQ=WEEKLY;BYDAY=MO,FR", Some(date(2025, 1, 17)), today), Some(date(2025, 1, 20)));
+        assert_eq!(
+            next("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO", Some(today), today),
+            Some(date(2025, 1, 27)),
+        );
+
+        assert_eq!(next("FREQ=DAILY", Some(date(2025, 1, 1)), today), Some(date(2025, 1, 16)));
+        assert_eq!(next("FREQ=DAILY;INTERVAL=3", None, today), Some(date(2025, 1, 18)));
+        assert_eq!(
+            next("FREQ=DAILY;INTERVAL=3;FROM=COMPLETION", Some(date(2025, 1, 1)), today),
+            Some(date(2025, 1, 18)),
+        );
+    }
+
+    #[test]
+    fn test_anchored() {
+        let monthly: Recurrence = "FREQ=MONTHLY".parse().unwrap();
+        assert_eq!(monthly.anchored(Some(date(2025, 1, 15))), monthly);
+        let anchored = monthly.anchored(Some(date(2025, 1, 31)));
+        assert_eq!(anchored.to_string(), "FREQ=MONTHLY;BYMONTHDAY=31");
+        let feb = anchored.next(Some(date(2025, 1, 31)), date(2025, 1, 31)).unwrap();
+        assert_eq!(feb, date(2025, 2, 28));
+        let mar = anchored.anchored(Some(feb)).next(Some(feb), feb).unwrap();
+        assert_eq!(mar, date(2025, 3, 31));
+        assert_eq!(anchored.next(Some(mar), mar), Some(date(2025, 4, 30)));
+
+        let yearly = "FREQ=YEARLY".parse::<Recurrence>().unwrap().anchored(Some(date(2024, 2, 29)));
+        assert_eq!(yearly.next(Some(date(2024, 2, 29)), date(2024, 2, 29)), Some(date(2025, 2, 28)));
+        assert_eq!(yearly.next(Some(date(2027, 2, 28)), date(2027, 2, 28)), Some(date(2028, 2, 29)));
+        assert!("FREQ=WEEKLY".parse::<Recurrence>().unwrap().anchored(Some(date(2025, 1, 31))).month_day.is_none());
+    }
+}
@@ -0,0 +1,121 @@
+use crate::db::{Task, TimeReport};
+
+pub fn format_duration(seconds: i64) -> String {
+    let seconds = seconds.max(0);
+    format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+pub fn render(report: &[TimeReport]) -> String {
+    let mut output = String::new();
+    let mut total = 0;
+    for (i, row) in report.iter().enumerate() {
+        let day = if i == 0 || report[i - 1].day != row.day {
+            row.day.format("%Y/%m/%d").to_string()
+        } else {
+            String::new()
+        };
+        output += &format!("{:>10} {:>9} {}\n", day, format_duration(row.seconds), row.subject);
+        total += row.seconds;
+
+        if report.get(i + 1).is_none_or(|next| next.day != row.day) {
+            let day_total = report
+                .iter()
+                .filter(|other| other.day == row.day)
+                .map(|other| other.seconds)
+                .sum();
+            output += &format!("{:>10} {:>9}\n\n", "", format_duration(day_total));
+        }
+    }
+    output += &format!("{:>10} {:>9}\n", "total", format_duration(total));
+
+    output
+}
+
+pub fn render_tasks(tasks: &[Task]) -> String {
+    let mut output = String::new();
+    for task in tasks {
+        let due = match task.due {
+            Some(due) => due.format("%Y/%m/%d").to_string(),
+            None => String::new(),
+        };
+        let mut line = format!(
+            "[{}] {:>10} {} {}",
+            if task.done { "x" } else { " " },
+            due,
+            task.priority.symbol(),
+            task.subject,
+        );
+        for tag in &task.tags {
+            line += &format!(" #{}", tag);
+        }
+        for field in &task.fields {
+            line += &format!(" {}={}", field.name, field.value);
+        }
+        output += line.trim_end();
+        output += "\n";
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{FieldValue, Priority, DEFAULT_PROJECT};
+    use crate::store::{MemoryStore, TaskStore};
+    use chrono::NaiveDate;
+
+    fn row(day: u32, subject: &str, seconds: i64) -> TimeReport {
+        TimeReport {
+            day: NaiveDate::from_ymd_opt(2025, 1, day).unwrap(),
+            task_id: 1,
+            subject: String::from(subject),
+            seconds,
+        }
+    }
+
+    #[test]
+    fn test_render_tasks() {
+        let db = MemoryStore::new();
+        let mut task = db.insert_one(DEFAULT_PROJECT, "invoice", "").unwrap().unwrap();
+        task.done = true;
+        task.due = NaiveDate::from_ymd_opt(2025, 1, 31);
+        task.priority = Priority::High;
+        task.tags = vec![String::from("work")];
+        task.set_field("points", Some(FieldValue::Number(3.0)));
+        let task = db.update_one(&task).unwrap().unwrap();
+        let other = db.insert_one(DEFAULT_PROJECT, "call", "").unwrap().unwrap();
+
+        assert_eq!(
+            render_tasks(&[task, other]),
+            "[x] 2025/01/31 !!! invoice #work points=3\n[ ]                call\n",
+        );
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(0), "0:00:00");
+        assert_eq!(format_duration(3 * 3600 + 5 * 60 + 9), "3:05:09");
+        assert_eq!(format_duration(-5), "0:00:00");
+    }
+
+    #[test]
+    fn test_render() {
+        let report = [row(15, "invoice", 5400), row(15, "report", 2700), row(16, "invoice", 600)];
+        assert_eq!(
+            render(&report),
+            [
+                "2025/01/15   1:30:00 invoice",
+                "             0:45:00 report",
+                "             2:15:00",
+                "",
+                "2025/01/16   0:10:00 invoice",
+                "             0:10:00",
+                "",
+                "     total   2:25:00",
+                "",
+            ]
+            .join("\n"),
+        );
+    }
+}
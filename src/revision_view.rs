@@ -0,0 +1,187 @@
+use chrono::Local;
+use ratatui::{
+    buffer::Buffer,
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::{Constraint, Direction, Layout, Rect},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph, Widget},
+};
+
+use crate::db::{Revision, Task};
+use crate::diff::{self, Diff};
+
+enum RevisionViewStatus {
+    Hiding,
+    Showing,
+}
+
+pub struct RevisionView {
+    status: RevisionViewStatus,
+    text: String,
+    revisions: Vec<Revision>,
+    current: usize,
+    revision: Option<Revision>,
+}
+
+fn text(subject: &str, body: &str) -> String {
+    format!("{}\n\n{}", subject, body)
+}
+
+impl RevisionView {
+    pub fn new() -> Self {
+        Self {
+            status: RevisionViewStatus::Hiding,
+            text: String::new(),
+            revisions: vec![],
+            current: 0,
+            revision: None,
+        }
+    }
+
+    pub fn handle_key_press_event(&mut self, key_event: KeyEvent) -> bool {
+        if let RevisionViewStatus::Hiding = self.status {
+            return false;
+        }
+
+        match key_event.code {
+            KeyCode::Char('k') => {
+                self.current = self.current.saturating_sub(1);
+            }
+            KeyCode::Char('j') if self.current + 1 < self.revisions.len() => {
+                self.current += 1;
+            }
+            KeyCode::Enter => {
+                self.revision = self.revisions.get(self.current).cloned();
+                self.hide();
+            }
+            KeyCode::Esc | KeyCode::Char('q') => self.hide(),
+            _ => (),
+        };
+
+        true
+    }
+
+    pub fn get_revision(&mut self) -> Option<Revision> {
+        self.revision.take()
+    }
+
+    pub fn start(&mut self, task: &Task, revisions: Vec<Revision>) {
+        self.text = text(&task.subject, &task.body);
+        self.revisions = revisions;
+        self.current = 0;
+        self.revision = None;
+
+        self.status = RevisionViewStatus::Showing;
+    }
+
+    fn hide(&mut self) {
+        self.status = RevisionViewStatus::Hiding;
+    }
+}
+
+impl Widget for &RevisionView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if let RevisionViewStatus::Hiding = self.status {
+            return;
+        }
+
+        let layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(10),
+                Constraint::Percentage(80),
+                Constraint::Percentage(10),
+            ])
+            .split(area);
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Percentage(10),
+                Constraint::Percentage(80),
+                Constraint::Percentage(10),
+            ])
+            .split(layout[1]);
+        let area = layout[1];
+
+        Clear.render(area, buf);
+        let width = area.width.saturating_sub(2) as usize;
+        let mut lines: Vec<_> = self
+            .revisions
+            .iter()
+            .enumerate()
+            .map(|(i, revision)| {
+                let created = revision.created.with_timezone(&Local).format("%Y/%m/%d %H:%M");
+                let row = format!("{} {}", created, revision.subject);
+                if i == self.current {
+                    Line::from(format!("{:<width$}", row).white().on_red())
+                } else {
+                    Line::from(row)
+                }
+            })
+            .collect();
+
+        if let Some(revision) = self.revisions.get(self.current) {
+            lines.push(Line::from(""));
+            let old = text(&revision.subject, &revision.body);
+            for line in diff::lines(&old, &self.text) {
+                lines.push(match line {
+                    Diff::Same(line) => Line::from(format!(" {}", line)),
+                    Diff::Removed(line) => Line::from(format!("-{}", line)).red(),
+                    Diff::Added(line) => Line::from(format!("+{}", line)).green(),
+                });
+            }
+        }
+
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .title(" history ")
+            .title_bottom(Line::from(" enter: revert ").right_aligned());
+        Paragraph::new(lines).block(block).render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::DEFAULT_PROJECT;
+    use crate::store::{MemoryStore, TaskStore};
+
+    #[test]
+    fn test_revision_view() {
+        let store = MemoryStore::new();
+        let mut task = store.insert_one(DEFAULT_PROJECT, "task", "one\ntwo").unwrap().unwrap();
+        task.body = String::from("one\nthree");
+        let task = store.update_one(&task).unwrap().unwrap();
+        let revisions = store.revisions(task.id).unwrap();
+        let created = revisions[0].created.with_timezone(&Local).format("%Y/%m/%d %H:%M");
+
+        let mut view = RevisionView::new();
+        assert!(!view.handle_key_press_event(KeyCode::Enter.into()));
+        view.start(&task, revisions);
+
+        let mut buf = Buffer::empty(Rect::new(0, 0, 30, 12));
+        view.render(buf.area, &mut buf);
+        let row = format!("{} task", created);
+        let expected = Buffer::with_lines(vec![
+            Line::from("                              "),
+            Line::from("   ┌ history ─────────────┐   "),
+            Line::from(vec!["   │".into(), format!("{:<22}", row).white().on_red(), "│   ".into()]),
+            Line::from("   │                      │   "),
+            Line::from("   │ task                 │   "),
+            Line::from("   │                      │   "),
+            Line::from("   │ one                  │   "),
+            Line::from(vec!["   │".into(), "-two".red(), "                  │   ".into()]),
+            Line::from(vec!["   │".into(), "+three".green(), "                │   ".into()]),
+            Line::from("   │                      │   "),
+            Line::from("   └─────── enter: revert ┘   "),
+            Line::from("                              "),
+        ]);
+        assert_eq!(buf, expected);
+
+        assert!(view.handle_key_press_event(KeyCode::Enter.into()));
+        let revision = view.get_revision().unwrap();
+        assert_eq!(revision.body, "one\ntwo");
+        assert!(!view.handle_key_press_event(KeyCode::Esc.into()));
+    }
+}
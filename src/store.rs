@@ -0,0 +1,351 @@
+use chrono::NaiveDate;
+use uuid::Uuid;
+
+use crate::db::{FieldValue, Project, Result, Revision, SearchHit, Task, TaskQuery, TimeEntry, TimeReport};
+
+mod memory;
+pub use memory::MemoryStore;
+
+pub trait TaskStore {
+    fn get_one(&self, id: i64) -> Result<Option<Task>>;
+    fn get_by_uuid(&self, uuid: &Uuid) -> Result<Option<Task>>;
+    fn insert_one(&self, project_id: i64, subject: &str, body: &str) -> Result<Option<Task>>;
+    fn insert_task(&self, task: &Task) -> Result<Option<Task>>;
+    fn update_one(&self, task: &Task) -> Result<Option<Task>>;
+    fn set_field(&self, id: i64, name: &str, value: Option<FieldValue>) -> Result<Option<Task>> {
+        match self.get_one(id)? {
+            Some(mut task) => {
+                task.set_field(name, value);
+                self.update_one(&task)
+            }
+            None => Ok(None),
+        }
+    }
+    fn delete_one(&self, id: i64) -> Result<Option<Task>>;
+    fn trash_one(&self, id: i64) -> Result<Option<Task>>;
+    fn restore_one(&self, id: i64) -> Result<Option<Task>>;
+    fn purge_one(&self, id: i64) -> Result<Option<Task>>;
+    fn archive_one(&self, id: i64) -> Result<Option<Task>>;
+    fn unarchive_one(&self, id: i64) -> Result<Option<Task>>;
+
+    fn query(&self, query: &TaskQuery) -> Result<Vec<Task>>;
+    fn search(&self, query: &TaskQuery) -> Result<Vec<SearchHit>>;
+    fn list_trash(&self) -> Result<Vec<Task>>;
+    fn purge_trash(&self, days: u32) -> Result<usize>;
+    fn list_archive(&self, project_id: i64) -> Result<Vec<Task>>;
+    fn archive_done(&self, project_id: Option<i64>, days: u32) -> Result<Vec<Task>>;
+
+    fn move_to(&self, id: i64, index: usize) -> Result<Option<(usize, usize)>>;
+    fn move_before(&self, id: i64, other: i64) -> Result<Option<(usize, usize)>>;
+    fn move_after(&self, id: i64, other: i64) -> Result<Option<(usize, usize)>>;
+
+    fn children(&self, id: i64) -> Result<Vec<Task>>;
+    fn move_under(&self, id: i64, parent_id: Option<i64>) -> Result<Option<Task>>;
+    fn add_dependency(&self, id: i64, blocker_id: i64) -> Result<Option<Task>>;
+    fn remove_dependency(&self, id: i64, blocker_id: i64) -> Result<Option<Task>>;
+    fn dependents(&self, id: i64) -> Result<Vec<Task>>;
+    fn spawn_next(&self, task: &Task, completed: NaiveDate) -> Result<Option<Task>>;
+    fn revisions(&self, task_id: i64) -> Result<Vec<Revision>>;
+    fn data_version(&self) -> Result<i64>;
+
+    fn get_project(&self, id: i64) -> Result<Option<Project>>;
+    fn list_projects(&self) -> Result<Vec<Project>>;
+    fn create_project(&self, name: &str) -> Result<Project>;
+    fn rename_project(&self, id: i64, name: &str) -> Result<Option<Project>>;
+    fn archive_project(&self, id: i64, archived: bool) -> Result<Option<Project>>;
+    fn delete_project(&self, id: i64) -> Result<Option<Project>>;
+
+    fn running_timer(&self) -> Result<Option<TimeEntry>>;
+    fn start_timer(&self, task_id: i64) -> Result<TimeEntry>;
+    fn stop_timer(&self) -> Result<Option<TimeEntry>>;
+    fn tracked(&self, task_id: i64) -> Result<i64>;
+    fn time_report(&self) -> Result<Vec<TimeReport>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use crate::db::{Db, DbError, Priority, Sort, DEFAULT_PROJECT, MATCH_END, MATCH_START};
+
+    fn stores() -> Vec<Box<dyn TaskStore>> {
+        vec![Box::new(Db::open(":memory:").unwrap()), Box::new(MemoryStore::new())]
+    }
+
+    fn subjects(tasks: &[Task]) -> Vec<&str> {
+        tasks.iter().map(|task| task.subject.as_str()).collect()
+    }
+
+    #[test]
+    fn test_tasks() {
+        for store in stores() {
+            let alpha = store.insert_one(DEFAULT_PROJECT, "alpha", "first").unwrap().unwrap();
+            let beta = store.insert_one(DEFAULT_PROJECT, "beta", "").unwrap().unwrap();
+            let gamma = store.insert_one(DEFAULT_PROJECT, "gamma", "").unwrap().unwrap();
+            assert_eq!((alpha.id, beta.id, gamma.id), (1, 2, 3));
+            assert_eq!((alpha.position, gamma.position), (0, 2));
+            assert_ne!(alpha.uuid, beta.uuid);
+            assert_eq!(store.get_by_uuid(&beta.uuid).unwrap().unwrap().id, beta.id);
+            assert!(store.get_by_uuid(&Uuid::new_v4()).unwrap().is_none());
+            let found = store.query(&TaskQuery::new().uuid(gamma.uuid)).unwrap();
+            assert_eq!(subjects(&found), ["gamma"]);
+
+            let mut task = beta.clone();
+            task.done = true;
+            task.tags = vec![String::from("b"), String::from("A"), String::from("a")];
+            task.priority = Priority::High;
+            let task = store.update_one(&task).unwrap().unwrap();
+            assert!(task.completed_at.is_some());
+            assert_eq!(task.tags, ["A", "b"]);
+
+            assert_eq!(store.move_to(gamma.id, 0).unwrap(), Some((2, 0)));
+            let all = store.query(&TaskQuery::new()).unwrap();
+            assert_eq!(subjects(&all), ["gamma", "alpha", "beta"]);
+            let sorted = store.query(&TaskQuery::new().sort(Sort::Priority)).unwrap();
+            assert_eq!(subjects(&sorted), ["beta", "gamma", "alpha"]);
+            let tagged = store.query(&TaskQuery::new().tag("a").done(true)).unwrap();
+            assert_eq!(subjects(&tagged), ["beta"]);
+            let tags = [String::from("A"), String::from("b"), String::from("a")];
+            let tagged = store.query(&TaskQuery::new().tag("B").tags(&tags)).unwrap();
+            assert_eq!(subjects(&tagged), ["beta"]);
+            let page = store.query(&TaskQuery::new().limit(1).offset(1)).unwrap();
+            assert_eq!(subjects(&page), ["alpha"]);
+
+            let hits = store.search(&TaskQuery::new().text("fir")).unwrap();
+            assert_eq!(hits.len(), 1);
+            assert_eq!(hits[0].body, format!("{}first{}", MATCH_START, MATCH_END));
+
+            store.trash_one(alpha.id).unwrap().unwrap();
+            assert_eq!(subjects(&store.list_trash().unwrap()), ["alpha"]);
+            assert_eq!(store.query(&TaskQuery::new()).unwrap().len(), 2);
+            assert_eq!(store.purge_trash(0).unwrap(), 1);
+            assert!(store.get_one(alpha.id).unwrap().is_none());
+
+            let mut task = store.get_one(gamma.id).unwrap().unwrap();
+            task.body = String::from("v2");
+            let task = store.update_one(&task).unwrap().unwrap();
+            store.update_one(&task).unwrap().unwrap();
+            let revisions = store.revisions(gamma.id).unwrap();
+            assert_eq!(revisions.len(), 1);
+            assert_eq!((revisions[0].subject.as_str(), revisions[0].body.as_str()), ("gamma", ""));
+
+            store.delete_one(beta.id).unwrap().unwrap();
+            assert!(store.insert_task(&beta).unwrap().is_some());
+            assert_eq!(store.get_by_uuid(&beta.uuid).unwrap().unwrap().subject, "beta");
+        }
+    }
+
+    #[test]
+    fn test_stale_after_trash_archive_and_move() {
+        for store in stores() {
+            let a = store.insert_one(DEFAULT_PROJECT, "a", "").unwrap().unwrap();
+            let b = store.insert_one(DEFAULT_PROJECT, "b", "").unwrap().unwrap();
+
+            let trashed = store.trash_one(a.id).unwrap().unwrap();
+            assert_eq!(trashed.version, a.version + 1);
+            assert!(matches!(store.update_one(&a), Err(DbError::StaleTask(_))));
+            let restored = store.restore_one(a.id).unwrap().unwrap();
+            let archived = store.archive_one(a.id).unwrap().unwrap();
+            let unarchived = store.unarchive_one(a.id).unwrap().unwrap();
+            assert_eq!(
+                [restored.version, archived.version, unarchived.version],
+                [a.version + 2, a.version + 3, a.version + 4],
+            );
+            assert!(matches!(store.update_one(&restored), Err(DbError::StaleTask(_))));
+
+            store.move_to(b.id, 0).unwrap().unwrap();
+            assert_eq!(store.get_one(b.id).unwrap().unwrap().version, b.version + 1);
+            assert!(matches!(store.update_one(&b), Err(DbError::StaleTask(_))));
+            assert!(store.update_one(&store.get_one(b.id).unwrap().unwrap()).unwrap().is_some());
+        }
+    }
+
+    #[test]
+    fn test_move_among_siblings() {
+        for store in stores() {
+            let project = store.create_project("work").unwrap();
+            let a = store.insert_one(DEFAULT_PROJECT, "a", "").unwrap().unwrap();
+            let other = store.insert_one(project.id, "other", "").unwrap().unwrap();
+            let child = store.insert_one(DEFAULT_PROJECT, "child", "").unwrap().unwrap();
+            let child = store.move_under(child.id, Some(a.id)).unwrap().unwrap();
+            let trashed = store.insert_one(DEFAULT_PROJECT, "trashed", "").unwrap().unwrap();
+            let trashed = store.trash_one(trashed.id).unwrap().unwrap();
+            let b = store.insert_one(DEFAULT_PROJECT, "b", "").unwrap().unwrap();
+
+            assert_eq!(store.move_to(b.id, 0).unwrap(), Some((1, 0)));
+            let roots = store.query(&TaskQuery::new().project(DEFAULT_PROJECT)).unwrap();
+            assert_eq!(subjects(&roots), ["b", "child", "a"]);
+            for task in [&other, &child, &trashed] {
+                let current = store.get_one(task.id).unwrap().unwrap();
+                assert_eq!((current.position, current.version), (task.position, task.version));
+            }
+            assert!(store.move_before(b.id, child.id).unwrap().is_none());
+            assert!(store.move_to(trashed.id, 0).unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn test_relations_touch_tasks() {
+        for store in stores() {
+            let backdate = |task: Task| {
+                store.delete_one(task.id).unwrap().unwrap();
+                let updated_at = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+                store.insert_task(&Task { updated_at, ..task }).unwrap().unwrap()
+            };
+            let project = store.create_project("work").unwrap();
+            let a = backdate(store.insert_one(project.id, "a", "").unwrap().unwrap());
+            let b = store.insert_one(DEFAULT_PROJECT, "b", "").unwrap().unwrap();
+
+            let added = store.add_dependency(a.id, b.id).unwrap().unwrap();
+            assert_eq!(added.version, a.version + 1);
+            assert!(added.updated_at > a.updated_at);
+            let added = backdate(added);
+            let same = store.add_dependency(a.id, b.id).unwrap().unwrap();
+            assert_eq!((same.version, same.updated_at), (added.version, added.updated_at));
+            let same = store.add_dependency(a.id, 42).unwrap().unwrap();
+            assert_eq!((same.version, same.updated_at), (added.version, added.updated_at));
+
+            let removed = store.remove_dependency(a.id, b.id).unwrap().unwrap();
+            assert_eq!(removed.version, added.version + 1);
+            assert!(removed.updated_at > added.updated_at);
+            let removed = backdate(removed);
+            let same = store.remove_dependency(a.id, b.id).unwrap().unwrap();
+            assert_eq!((same.version, same.updated_at), (removed.version, removed.updated_at));
+
+            store.delete_project(project.id).unwrap().unwrap();
+            let moved = store.get_one(a.id).unwrap().unwrap();
+            assert_eq!(moved.version, removed.version + 1);
+            assert!(moved.updated_at > removed.updated_at);
+        }
+    }
+
+    #[test]
+    fn test_missing_references() {
+        for store in stores() {
+            assert!(matches!(store.insert_one(42, "a", ""), Err(DbError::Sqlite(_))));
+            assert!(matches!(store.start_timer(42), Err(DbError::Sqlite(_))));
+            let foreign_key = |result: Result<Option<Task>>| match result {
+                Err(DbError::Sqlite(error)) => error.code == Some(sqlite3_sys::SQLITE_CONSTRAINT as isize),
+                _ => false,
+            };
+            let mut task = store.insert_one(DEFAULT_PROJECT, "a", "").unwrap().unwrap();
+            task.project_id = 42;
+            assert!(foreign_key(store.update_one(&task)));
+            task.project_id = DEFAULT_PROJECT;
+            task.parent_id = Some(42);
+            assert!(foreign_key(store.update_one(&task)));
+            assert!(foreign_key(store.move_under(task.id, Some(42))));
+            task.id += 1;
+            task.uuid = Uuid::new_v4();
+            assert!(foreign_key(store.insert_task(&task)));
+            task.parent_id = None;
+            task.project_id = 42;
+            assert!(foreign_key(store.insert_task(&task)));
+            assert_eq!(store.query(&TaskQuery::new()).unwrap().len(), 1);
+            assert!(store.get_one(task.id - 1).unwrap().unwrap().parent_id.is_none());
+        }
+
+        let store = MemoryStore::new();
+        let version = store.data_version().unwrap();
+        store.insert_one(DEFAULT_PROJECT, "a", "").unwrap().unwrap();
+        assert_ne!(store.data_version().unwrap(), version);
+    }
+
+    #[test]
+    fn test_fields() {
+        for store in stores() {
+            let a = store.insert_one(DEFAULT_PROJECT, "a", "").unwrap().unwrap();
+            let b = store.insert_one(DEFAULT_PROJECT, "b", "").unwrap().unwrap();
+            let c = store.insert_one(DEFAULT_PROJECT, "c", "").unwrap().unwrap();
+            store.set_field(a.id, "points", Some(FieldValue::parse("5"))).unwrap().unwrap();
+            store.set_field(a.id, "customer", Some(FieldValue::parse("ACME"))).unwrap().unwrap();
+            let task = store.set_field(b.id, "Points", Some(FieldValue::parse("3"))).unwrap().unwrap();
+            assert_eq!(task.field("points"), Some(&FieldValue::Number(3.0)));
+            assert!(store.set_field(42, "points", None).unwrap().is_none());
+
+            let task = store.get_one(a.id).unwrap().unwrap();
+            let names: Vec<_> = task.fields.iter().map(|field| field.name.as_str()).collect();
+            assert_eq!(names, ["customer", "points"]);
+
+            let sorted = store.query(&TaskQuery::new().sort_field("points")).unwrap();
+            assert_eq!(subjects(&sorted), ["b", "a", "c"]);
+            let found = store.query(&TaskQuery::new().field("POINTS", FieldValue::Number(5.0))).unwrap();
+            assert_eq!(subjects(&found), ["a"]);
+            let found = store.query(&TaskQuery::new().field("points", FieldValue::parse("\"5\""))).unwrap();
+            assert!(found.is_empty());
+
+            let task = store.set_field(a.id, "points", None).unwrap().unwrap();
+            assert!(task.field("points").is_none());
+            store.delete_one(a.id).unwrap().unwrap();
+            store.insert_task(&task).unwrap().unwrap();
+            assert_eq!(store.get_one(a.id).unwrap().unwrap().fields, task.fields);
+            assert!(store.get_one(c.id).unwrap().unwrap().fields.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_archive() {
+        for store in stores() {
+            let project = store.create_project("work").unwrap();
+            let mut ids = vec![];
+            for (project_id, subject) in [(DEFAULT_PROJECT, "a"), (DEFAULT_PROJECT, "b"), (project.id, "c")] {
+                let mut task = store.insert_one(project_id, subject, "").unwrap().unwrap();
+                task.done = subject != "b";
+                ids.push(store.update_one(&task).unwrap().unwrap().id);
+            }
+
+            assert!(store.archive_done(None, 1).unwrap().is_empty());
+            let archived = store.archive_done(Some(DEFAULT_PROJECT), 0).unwrap();
+            assert_eq!(subjects(&archived), ["a"]);
+            assert!(archived[0].archived_at.is_some());
+            assert_eq!(subjects(&store.query(&TaskQuery::new()).unwrap()), ["b", "c"]);
+            assert_eq!(subjects(&store.list_archive(DEFAULT_PROJECT).unwrap()), ["a"]);
+            assert!(store.list_archive(project.id).unwrap().is_empty());
+
+            assert!(store.archive_one(ids[0]).unwrap().is_none());
+            store.archive_one(ids[1]).unwrap().unwrap();
+            store.unarchive_one(ids[0]).unwrap().unwrap();
+            assert!(store.unarchive_one(ids[0]).unwrap().is_none());
+            assert_eq!(subjects(&store.query(&TaskQuery::new()).unwrap()), ["a", "c"]);
+        }
+    }
+
+    #[test]
+    fn test_relations() {
+        for store in stores() {
+            let parent = store.insert_one(DEFAULT_PROJECT, "parent", "").unwrap().unwrap();
+            let child = store.insert_one(DEFAULT_PROJECT, "child", "").unwrap().unwrap();
+            store.move_under(child.id, Some(parent.id)).unwrap().unwrap();
+            assert_eq!(subjects(&store.children(parent.id).unwrap()), ["child"]);
+            assert!(matches!(
+                store.move_under(parent.id, Some(child.id)),
+                Err(DbError::ParentCycle { .. }),
+            ));
+
+            let task = store.add_dependency(parent.id, child.id).unwrap().unwrap();
+            assert!(task.blocked);
+            assert_eq!(subjects(&store.dependents(child.id).unwrap()), ["parent"]);
+            assert!(matches!(
+                store.add_dependency(child.id, parent.id),
+                Err(DbError::DependencyCycle { .. }),
+            ));
+            let task = store.remove_dependency(parent.id, child.id).unwrap().unwrap();
+            assert!(task.blockers.is_empty());
+
+            let project = store.create_project("work").unwrap();
+            assert!(matches!(store.create_project("Work"), Err(DbError::ProjectExists(_))));
+            let mut task = store.get_one(child.id).unwrap().unwrap();
+            task.project_id = project.id;
+            store.update_one(&task).unwrap().unwrap();
+            store.delete_project(project.id).unwrap().unwrap();
+            assert_eq!(store.get_one(child.id).unwrap().unwrap().project_id, DEFAULT_PROJECT);
+            assert!(matches!(store.delete_project(DEFAULT_PROJECT), Err(DbError::DefaultProject)));
+
+            let entry = store.start_timer(child.id).unwrap();
+            assert_eq!(store.running_timer().unwrap().unwrap().id, entry.id);
+            assert!(store.stop_timer().unwrap().unwrap().stopped_at.is_some());
+            assert!(store.running_timer().unwrap().is_none());
+            assert_eq!(store.time_report().unwrap().len(), 1);
+        }
+    }
+}
@@ -0,0 +1,835 @@
+use std::cell::{RefCell, RefMut};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashSet};
+
+use chrono::{DateTime, Days, Local, NaiveDate, SubsecRound, Utc};
+use uuid::Uuid;
+
+use super::TaskStore;
+use crate::db::{
+    DbError, Field, Priority, Project, Result, Revision, SearchHit, Task, TaskQuery, TimeEntry, TimeReport,
+    DEFAULT_PROJECT, MATCH_END, MATCH_START,
+};
+
+const SNIPPET_TOKENS: usize = 8;
+
+#[derive(Default)]
+struct State {
+    tasks: BTreeMap<i64, Task>,
+    projects: BTreeMap<i64, Project>,
+    time_entries: Vec<TimeEntry>,
+    revisions: Vec<Revision>,
+    last_task: i64,
+    last_project: i64,
+    last_time_entry: i64,
+    last_revision: i64,
+    writes: i64,
+}
+
+pub struct MemoryStore {
+    state: RefCell<State>,
+}
+
+fn now() -> DateTime<Utc> {
+    Utc::now().trunc_subsecs(0)
+}
+
+fn touch(task: &mut Task) {
+    task.version += 1;
+    task.updated_at = now();
+}
+
+// what SQLite reports for a row pointing to a missing project or task
+fn foreign_key_error() -> DbError {
+    DbError::Sqlite(sqlite::Error {
+        code: Some(sqlite3_sys::SQLITE_CONSTRAINT as isize),
+        message: Some(String::from("FOREIGN KEY constraint failed")),
+    })
+}
+
+fn seconds(entry: &TimeEntry) -> i64 {
+    (entry.stopped_at.unwrap_or_else(now) - entry.started_at).num_seconds()
+}
+
+fn tokens(text: &str) -> Vec<(usize, usize)> {
+    let mut tokens = vec![];
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                tokens.push((s, i));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        tokens.push((s, text.len()));
+    }
+
+    tokens
+}
+
+fn matches_term(token: &str, terms: &[String]) -> bool {
+    let token = token.to_lowercase();
+    terms.iter().any(|term| token.starts_with(term.as_str()))
+}
+
+fn count_matches(text: &str, terms: &[String]) -> usize {
+    tokens(text)
+        .into_iter()
+        .filter(|&(start, end)| matches_term(&text[start..end], terms))
+        .count()
+}
+
+fn highlight(text: &str, terms: &[String]) -> String {
+    let mut output = String::new();
+    let mut last = 0;
+    for (start, end) in tokens(text) {
+        if matches_term(&text[start..end], terms) {
+            output.push_str(&text[last..start]);
+            output.push(MATCH_START);
+            output.push_str(&text[start..end]);
+            output.push(MATCH_END);
+            last = end;
+        }
+    }
+    output.push_str(&text[last..]);
+
+    output
+}
+
+fn snippet(text: &str, terms: &[String]) -> String {
+    let tokens = tokens(text);
+    let first = match tokens
+        .iter()
+        .position(|&(start, end)| matches_term(&text[start..end], terms))
+    {
+        Some(first) => first,
+        None => return String::new(),
+    };
+    let from = first.min(tokens.len().saturating_sub(SNIPPET_TOKENS));
+    let to = (from + SNIPPET_TOKENS).min(tokens.len());
+
+    let start = if from == 0 { 0 } else { tokens[from].0 };
+    let end = if to == tokens.len() { text.len() } else { tokens[to - 1].1 };
+    let mut output = highlight(&text[start..end], terms);
+    if from > 0 {
+        output.insert_str(0, "...");
+    }
+    if to < tokens.len() {
+        output.push_str("...");
+    }
+
+    output
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        let mut state = State::default();
+        state.projects.insert(
+            DEFAULT_PROJECT,
+            Project {
+                id: DEFAULT_PROJECT,
+                name: String::from("todo"),
+                archived: false,
+            },
+        );
+        state.last_project = DEFAULT_PROJECT;
+
+        Self {
+            state: RefCell::new(state),
+        }
+    }
+
+    // every write changes data_version, as a commit from another connection does in SQLite
+    fn write(&self) -> RefMut<'_, State> {
+        let mut state = self.state.borrow_mut();
+        state.writes += 1;
+        state
+    }
+}
+
+impl State {
+    fn read(&self, task: &Task) -> Task {
+        let mut task = task.clone();
+        task.blocked = task
+            .blockers
+            .iter()
+            .filter_map(|id| self.tasks.get(id))
+            .any(|blocker| !blocker.done && blocker.deleted_at.is_none());
+        task
+    }
+
+    fn get(&self, id: i64) -> Option<Task> {
+        self.tasks.get(&id).map(|task| self.read(task))
+    }
+
+    fn list<F>(&self, filter: F) -> Vec<Task>
+    where
+        F: Fn(&Task) -> bool,
+    {
+        let mut tasks: Vec<_> = self
+            .tasks
+            .values()
+            .filter(|task| filter(task))
+            .map(|task| self.read(task))
+            .collect();
+        tasks.sort_by_key(|task| (task.position, task.id));
+        tasks
+    }
+
+    fn tag_names(&self, tags: &[String]) -> Vec<String> {
+        let mut names: Vec<String> = vec![];
+        for tag in tags {
+            if names.iter().any(|name| name.eq_ignore_ascii_case(tag)) {
+                continue;
+            }
+            let existing = self
+                .tasks
+                .values()
+                .flat_map(|task| &task.tags)
+                .find(|name| name.eq_ignore_ascii_case(tag));
+            names.push(existing.unwrap_or(tag).clone());
+        }
+        names.sort_by_key(|name| name.to_ascii_lowercase());
+
+        names
+    }
+
+    fn check_parent(&self, id: i64, parent_id: i64) -> Result<()> {
+        let mut seen = HashSet::new();
+        let mut current = Some(parent_id);
+        while let Some(ancestor) = current.filter(|ancestor| seen.insert(*ancestor)) {
+            if ancestor == id {
+                return Err(DbError::ParentCycle { id, parent_id });
+            }
+            current = self.tasks.get(&ancestor).and_then(|task| task.parent_id);
+        }
+
+        Ok(())
+    }
+
+    fn check_blocker(&self, id: i64, blocker_id: i64) -> Result<()> {
+        let mut seen = HashSet::new();
+        let mut stack = vec![blocker_id];
+        while let Some(current) = stack.pop() {
+            if current == id {
+                return Err(DbError::DependencyCycle { id, blocker_id });
+            }
+            if seen.insert(current) {
+                if let Some(task) = self.tasks.get(&current) {
+                    stack.extend(&task.blockers);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn blockers(&self, id: i64, blockers: &[i64]) -> Result<Vec<i64>> {
+        for blocker_id in blockers {
+            self.check_blocker(id, *blocker_id)?;
+        }
+        let mut blockers: Vec<_> = blockers
+            .iter()
+            .copied()
+            .filter(|blocker_id| self.tasks.contains_key(blocker_id))
+            .collect();
+        blockers.sort();
+        blockers.dedup();
+
+        Ok(blockers)
+    }
+
+    fn remove(&mut self, id: i64) -> Option<Task> {
+        let task = self.get(id)?;
+        self.tasks.remove(&id);
+        for other in self.tasks.values_mut() {
+            other.blockers.retain(|blocker_id| *blocker_id != id);
+            if other.parent_id == Some(id) {
+                other.parent_id = None;
+            }
+        }
+        self.time_entries.retain(|entry| entry.task_id != id);
+        self.revisions.retain(|revision| revision.task_id != id);
+
+        Some(task)
+    }
+
+    fn reorder<F>(&mut self, id: i64, target: F) -> Option<(usize, usize)>
+    where
+        F: FnOnce(&[i64]) -> Option<usize>,
+    {
+        let task = self.tasks.get(&id)?;
+        let mut tasks: Vec<_> = self
+            .tasks
+            .values()
+            .filter(|other| other.project_id == task.project_id && other.parent_id == task.parent_id)
+            .filter(|other| other.deleted_at.is_none() && other.archived_at.is_none())
+            .collect();
+        tasks.sort_by_key(|task| (task.position, task.id));
+        let mut ids: Vec<_> = tasks.iter().map(|task| task.id).collect();
+        let positions: Vec<_> = tasks.iter().map(|task| task.position).collect();
+
+        let from = ids.iter().position(|&i| i == id)?;
+        ids.remove(from);
+        let to = target(&ids)?.min(ids.len());
+        ids.insert(to, id);
+
+        for (position, id) in positions.into_iter().zip(&ids) {
+            if let Some(task) = self.tasks.get_mut(id).filter(|task| task.position != position) {
+                task.position = position;
+                touch(task);
+            }
+        }
+
+        Some((from, to))
+    }
+
+    fn check_project(&self, id: i64) -> Result<()> {
+        if !self.projects.contains_key(&id) {
+            return Err(foreign_key_error());
+        }
+
+        Ok(())
+    }
+
+    fn check_task(&self, id: i64) -> Result<()> {
+        if !self.tasks.contains_key(&id) {
+            return Err(foreign_key_error());
+        }
+
+        Ok(())
+    }
+
+    fn check_project_name(&self, id: i64, name: &str) -> Result<()> {
+        let exists = self
+            .projects
+            .values()
+            .any(|project| project.id != id && project.name.eq_ignore_ascii_case(name));
+        if exists {
+            return Err(DbError::ProjectExists(name.to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+impl TaskStore for MemoryStore {
+    fn get_one(&self, id: i64) -> Result<Option<Task>> {
+        Ok(self.state.borrow().get(id))
+    }
+
+    fn get_by_uuid(&self, uuid: &Uuid) -> Result<Option<Task>> {
+        let state = self.state.borrow();
+        Ok(state
+            .tasks
+            .values()
+            .find(|task| task.uuid == *uuid)
+            .and_then(|task| state.get(task.id)))
+    }
+
+    fn insert_one(&self, project_id: i64, subject: &str, body: &str) -> Result<Option<Task>> {
+        let mut state = self.write();
+        state.check_project(project_id)?;
+        state.last_task += 1;
+        let now = now();
+        let task = Task {
+            id: state.last_task,
+            uuid: Uuid::new_v4(),
+            done: false,
+            subject: subject.to_string(),
+            body: body.to_string(),
+            created: now,
+            updated_at: now,
+            completed_at: None,
+            due: None,
+            deleted_at: None,
+            archived_at: None,
+            position: state.tasks.values().map(|task| task.position + 1).max().unwrap_or(0),
+            tags: vec![],
+            fields: vec![],
+            project_id,
+            parent_id: None,
+            blockers: vec![],
+            blocked: false,
+            priority: Priority::None,
+            recurrence: None,
+            version: 0,
+        };
+        let id = task.id;
+        state.tasks.insert(id, task);
+
+        Ok(state.get(id))
+    }
+
+    fn insert_task(&self, task: &Task) -> Result<Option<Task>> {
+        let mut state = self.write();
+        if state.tasks.contains_key(&task.id) {
+            return Err(DbError::TaskExists(task.id));
+        }
+        state.check_project(task.project_id)?;
+        if let Some(parent_id) = task.parent_id {
+            state.check_task(parent_id)?;
+        }
+        let mut task = task.clone();
+        task.tags = state.tag_names(&task.tags);
+        task.fields = Field::normalize(&task.fields);
+        task.blockers = state.blockers(task.id, &task.blockers)?;
+        state.last_task = state.last_task.max(task.id);
+        let id = task.id;
+        state.tasks.insert(id, task);
+
+        Ok(state.get(id))
+    }
+
+    fn update_one(&self, task: &Task) -> Result<Option<Task>> {
+        let mut state = self.write();
+        if let Some(parent_id) = task.parent_id {
+            state.check_parent(task.id, parent_id)?;
+        }
+        match state.tasks.get(&task.id) {
+            Some(current) if current.version != task.version => {
+                return Err(DbError::StaleTask(task.id))
+            }
+            Some(_) => (),
+            None => return Ok(None),
+        }
+        state.check_project(task.project_id)?;
+        if let Some(parent_id) = task.parent_id {
+            state.check_task(parent_id)?;
+        }
+        let tags = state.tag_names(&task.tags);
+        let blockers = state.blockers(task.id, &task.blockers)?;
+
+        let now = now();
+        let revision = state
+            .tasks
+            .get(&task.id)
+            .filter(|current| current.subject != task.subject || current.body != task.body)
+            .map(|current| (current.subject.clone(), current.body.clone()));
+        if let Some((subject, body)) = revision {
+            state.last_revision += 1;
+            let id = state.last_revision;
+            state.revisions.push(Revision {
+                id,
+                task_id: task.id,
+                subject,
+                body,
+                created: now,
+            });
+        }
+        if let Some(current) = state.tasks.get_mut(&task.id) {
+            current.completed_at = match (task.done, current.done) {
+                (false, _) => None,
+                (true, true) => current.completed_at,
+                (true, false) => Some(now),
+            };
+            current.done = task.done;
+            current.subject = task.subject.clone();
+            current.body = task.body.clone();
+            current.due = task.due;
+            current.project_id = task.project_id;
+            current.parent_id = task.parent_id;
+            current.priority = task.priority;
+            current.recurrence = task.recurrence.clone();
+            current.fields = Field::normalize(&task.fields);
+            current.updated_at = now;
+            current.tags = tags;
+            current.blockers = blockers;
+            current.version += 1;
+        }
+
+        Ok(state.get(task.id))
+    }
+
+    fn delete_one(&self, id: i64) -> Result<Option<Task>> {
+        Ok(self.write().remove(id))
+    }
+
+    fn trash_one(&self, id: i64) -> Result<Option<Task>> {
+        let mut state = self.write();
+        match state.tasks.get_mut(&id) {
+            Some(task) if task.deleted_at.is_none() => {
+                task.deleted_at = Some(now());
+                touch(task);
+            }
+            _ => return Ok(None),
+        }
+
+        Ok(state.get(id))
+    }
+
+    fn restore_one(&self, id: i64) -> Result<Option<Task>> {
+        let mut state = self.write();
+        match state.tasks.get_mut(&id) {
+            Some(task) if task.deleted_at.is_some() => {
+                task.deleted_at = None;
+                touch(task);
+            }
+            _ => return Ok(None),
+        }
+
+        Ok(state.get(id))
+    }
+
+    fn purge_one(&self, id: i64) -> Result<Option<Task>> {
+        let mut state = self.write();
+        match state.tasks.get(&id) {
+            Some(task) if task.deleted_at.is_some() => Ok(state.remove(id)),
+            _ => Ok(None),
+        }
+    }
+
+    fn archive_one(&self, id: i64) -> Result<Option<Task>> {
+        let mut state = self.write();
+        match state.tasks.get_mut(&id) {
+            Some(task) if task.archived_at.is_none() => {
+                task.archived_at = Some(now());
+                touch(task);
+            }
+            _ => return Ok(None),
+        }
+
+        Ok(state.get(id))
+    }
+
+    fn unarchive_one(&self, id: i64) -> Result<Option<Task>> {
+        let mut state = self.write();
+        match state.tasks.get_mut(&id) {
+            Some(task) if task.archived_at.is_some() => {
+                task.archived_at = None;
+                touch(task);
+            }
+            _ => return Ok(None),
+        }
+
+        Ok(state.get(id))
+    }
+
+    fn query(&self, query: &TaskQuery) -> Result<Vec<Task>> {
+        let terms = query.terms();
+        let state = self.state.borrow();
+        let mut tasks = state.list(|task| {
+            query.matches(task)
+                && terms.iter().all(|term| {
+                    let term = [term.clone()];
+                    count_matches(&task.subject, &term) + count_matches(&task.body, &term) > 0
+                })
+        });
+        tasks.sort_by(|a, b| query.compare(a, b));
+
+        Ok(query.paginate(tasks))
+    }
+
+    fn search(&self, query: &TaskQuery) -> Result<Vec<SearchHit>> {
+        let terms = query.terms();
+        if terms.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut hits: Vec<_> = self
+            .query(&query.clone().limit(usize::MAX).offset(0))?
+            .into_iter()
+            .map(|task| {
+                let rank = 10 * count_matches(&task.subject, &terms) + count_matches(&task.body, &terms);
+                let hit = SearchHit {
+                    subject: highlight(&task.subject, &terms),
+                    body: highlight(&task.body, &terms),
+                    snippet: snippet(&task.body, &terms),
+                    task,
+                };
+                (rank, hit)
+            })
+            .collect();
+        hits.sort_by_key(|(rank, hit)| (Reverse(*rank), hit.task.position));
+
+        Ok(query.paginate(hits.into_iter().map(|(_, hit)| hit).collect()))
+    }
+
+    fn list_trash(&self) -> Result<Vec<Task>> {
+        let state = self.state.borrow();
+        let mut tasks = state.list(|task| task.deleted_at.is_some());
+        tasks.sort_by_key(|task| Reverse((task.deleted_at, task.id)));
+
+        Ok(tasks)
+    }
+
+    fn list_archive(&self, project_id: i64) -> Result<Vec<Task>> {
+        let state = self.state.borrow();
+        let mut tasks = state.list(|task| {
+            task.archived_at.is_some() && task.deleted_at.is_none() && task.project_id == project_id
+        });
+        tasks.sort_by_key(|task| Reverse((task.archived_at, task.id)));
+
+        Ok(tasks)
+    }
+
+    fn archive_done(&self, project_id: Option<i64>, days: u32) -> Result<Vec<Task>> {
+        let mut state = self.write();
+        let now = now();
+        let cutoff = now.checked_sub_days(Days::new(days.into()));
+        let ids: Vec<_> = state
+            .tasks
+            .values_mut()
+            .filter(|task| task.done && task.archived_at.is_none() && task.deleted_at.is_none())
+            .filter(|task| Some(task.completed_at.unwrap_or(task.updated_at)) <= cutoff)
+            .filter(|task| project_id.is_none_or(|project_id| task.project_id == project_id))
+            .map(|task| {
+                task.archived_at = Some(now);
+                touch(task);
+                task.id
+            })
+            .collect();
+
+        Ok(ids.into_iter().filter_map(|id| state.get(id)).collect())
+    }
+
+    fn purge_trash(&self, days: u32) -> Result<usize> {
+        let mut state = self.write();
+        let cutoff = now().checked_sub_days(Days::new(days.into()));
+        let ids: Vec<_> = state
+            .tasks
+            .values()
+            .filter(|task| task.deleted_at.is_some_and(|deleted_at| Some(deleted_at) <= cutoff))
+            .map(|task| task.id)
+            .collect();
+        for id in &ids {
+            state.remove(*id);
+        }
+
+        Ok(ids.len())
+    }
+
+    fn move_to(&self, id: i64, index: usize) -> Result<Option<(usize, usize)>> {
+        Ok(self.write().reorder(id, |_| Some(index)))
+    }
+
+    fn move_before(&self, id: i64, other: i64) -> Result<Option<(usize, usize)>> {
+        Ok(self
+            .write()
+            .reorder(id, |ids| ids.iter().position(|&i| i == other)))
+    }
+
+    fn move_after(&self, id: i64, other: i64) -> Result<Option<(usize, usize)>> {
+        Ok(self
+            .write()
+            .reorder(id, |ids| ids.iter().position(|&i| i == other).map(|i| i + 1)))
+    }
+
+    fn children(&self, id: i64) -> Result<Vec<Task>> {
+        let state = self.state.borrow();
+        Ok(state.list(|task| task.deleted_at.is_none() && task.parent_id == Some(id)))
+    }
+
+    fn move_under(&self, id: i64, parent_id: Option<i64>) -> Result<Option<Task>> {
+        match self.get_one(id)? {
+            Some(mut task) => {
+                task.parent_id = parent_id;
+                self.update_one(&task)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn add_dependency(&self, id: i64, blocker_id: i64) -> Result<Option<Task>> {
+        let mut state = self.write();
+        state.check_blocker(id, blocker_id)?;
+        if state.tasks.contains_key(&blocker_id) {
+            if let Some(task) = state.tasks.get_mut(&id).filter(|task| !task.blockers.contains(&blocker_id)) {
+                task.blockers.push(blocker_id);
+                task.blockers.sort();
+                touch(task);
+            }
+        }
+
+        Ok(state.get(id))
+    }
+
+    fn remove_dependency(&self, id: i64, blocker_id: i64) -> Result<Option<Task>> {
+        let mut state = self.write();
+        if let Some(task) = state.tasks.get_mut(&id).filter(|task| task.blockers.contains(&blocker_id)) {
+            task.blockers.retain(|other| *other != blocker_id);
+            touch(task);
+        }
+
+        Ok(state.get(id))
+    }
+
+    fn dependents(&self, id: i64) -> Result<Vec<Task>> {
+        let state = self.state.borrow();
+        Ok(state.list(|task| task.deleted_at.is_none() && task.blockers.contains(&id)))
+    }
+
+    fn spawn_next(&self, task: &Task, completed: NaiveDate) -> Result<Option<Task>> {
+        let due = match &task.recurrence {
+            Some(recurrence) => recurrence.next(task.due, completed),
+            None => None,
+        };
+        if due.is_none() {
+            return Ok(None);
+        }
+
+        match self.insert_one(task.project_id, &task.subject, &task.body)? {
+            Some(mut next) => {
+                next.due = due;
+                next.tags = task.tags.clone();
+                next.parent_id = task.parent_id;
+                next.priority = task.priority;
+                next.recurrence = task.recurrence.as_ref().map(|recurrence| recurrence.anchored(task.due));
+                self.update_one(&next)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn revisions(&self, task_id: i64) -> Result<Vec<Revision>> {
+        let state = self.state.borrow();
+        Ok(state
+            .revisions
+            .iter()
+            .rev()
+            .filter(|revision| revision.task_id == task_id)
+            .cloned()
+            .collect())
+    }
+
+    fn data_version(&self) -> Result<i64> {
+        Ok(self.state.borrow().writes)
+    }
+
+    fn get_project(&self, id: i64) -> Result<Option<Project>> {
+        Ok(self.state.borrow().projects.get(&id).cloned())
+    }
+
+    fn list_projects(&self) -> Result<Vec<Project>> {
+        let mut projects: Vec<_> = self.state.borrow().projects.values().cloned().collect();
+        projects.sort_by_key(|project| {
+            (project.archived, project.id != DEFAULT_PROJECT, project.name.to_ascii_lowercase())
+        });
+
+        Ok(projects)
+    }
+
+    fn create_project(&self, name: &str) -> Result<Project> {
+        let mut state = self.write();
+        state.check_project_name(0, name)?;
+        state.last_project += 1;
+        let project = Project {
+            id: state.last_project,
+            name: name.to_string(),
+            archived: false,
+        };
+        state.projects.insert(project.id, project.clone());
+
+        Ok(project)
+    }
+
+    fn rename_project(&self, id: i64, name: &str) -> Result<Option<Project>> {
+        let mut state = self.write();
+        state.check_project_name(id, name)?;
+        let project = state.projects.get_mut(&id).map(|project| {
+            project.name = name.to_string();
+            project.clone()
+        });
+
+        Ok(project)
+    }
+
+    fn archive_project(&self, id: i64, archived: bool) -> Result<Option<Project>> {
+        let mut state = self.write();
+        let project = state.projects.get_mut(&id).map(|project| {
+            project.archived = archived;
+            project.clone()
+        });
+
+        Ok(project)
+    }
+
+    fn delete_project(&self, id: i64) -> Result<Option<Project>> {
+        if id == DEFAULT_PROJECT {
+            return Err(DbError::DefaultProject);
+        }
+
+        let mut state = self.write();
+        for task in state.tasks.values_mut().filter(|task| task.project_id == id) {
+            task.project_id = DEFAULT_PROJECT;
+            touch(task);
+        }
+
+        Ok(state.projects.remove(&id))
+    }
+
+    fn running_timer(&self) -> Result<Option<TimeEntry>> {
+        let state = self.state.borrow();
+        Ok(state.time_entries.iter().find(|entry| entry.stopped_at.is_none()).cloned())
+    }
+
+    fn start_timer(&self, task_id: i64) -> Result<TimeEntry> {
+        if !self.state.borrow().tasks.contains_key(&task_id) {
+            return Err(foreign_key_error());
+        }
+        self.stop_timer()?;
+        let mut state = self.write();
+        state.last_time_entry += 1;
+        let entry = TimeEntry {
+            id: state.last_time_entry,
+            task_id,
+            started_at: now(),
+            stopped_at: None,
+        };
+        state.time_entries.push(entry.clone());
+
+        Ok(entry)
+    }
+
+    fn stop_timer(&self) -> Result<Option<TimeEntry>> {
+        let mut state = self.write();
+        let entry = state
+            .time_entries
+            .iter_mut()
+            .find(|entry| entry.stopped_at.is_none())
+            .map(|entry| {
+                entry.stopped_at = Some(entry.started_at.max(now()));
+                entry.clone()
+            });
+
+        Ok(entry)
+    }
+
+    fn tracked(&self, task_id: i64) -> Result<i64> {
+        let state = self.state.borrow();
+        Ok(state
+            .time_entries
+            .iter()
+            .filter(|entry| entry.task_id == task_id)
+            .map(seconds)
+            .sum())
+    }
+
+    fn time_report(&self) -> Result<Vec<TimeReport>> {
+        let state = self.state.borrow();
+        let mut days: BTreeMap<(NaiveDate, i64), i64> = BTreeMap::new();
+        for entry in &state.time_entries {
+            let day = entry.started_at.with_timezone(&Local).date_naive();
+            *days.entry((day, entry.task_id)).or_default() += seconds(entry);
+        }
+
+        let mut report: Vec<_> = days
+            .into_iter()
+            .filter_map(|((day, task_id), seconds)| {
+                Some(TimeReport {
+                    day,
+                    task_id,
+                    subject: state.tasks.get(&task_id)?.subject.clone(),
+                    seconds,
+                })
+            })
+            .collect();
+        report.sort_by_key(|row| (row.day, Reverse(row.seconds), row.task_id));
+
+        Ok(report)
+    }
+}
@@ -1,66 +1,262 @@
 use std::cell::RefCell;
 use std::cmp;
+use std::collections::{HashMap, HashSet};
 use std::io;
 use std::mem;
+use std::time::Duration;
 
+use chrono::{DateTime, Local, NaiveDate, Utc};
 use ratatui::{
     buffer::Buffer,
-    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
     layout::{Constraint, Direction as LayoutDirection, Layout, Rect},
-    style::Stylize,
-    text::Line,
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
     widgets::{Block, Borders, Paragraph, Widget},
     DefaultTerminal, Frame,
 };
 
 use crate::{
     date_picker::DatePicker,
-    db::{Db, Task},
+    db::{
+        Field, FieldValue, Project, Result, Revision, SearchHit, Sort, Task, TaskQuery, TimeEntry,
+        DEFAULT_PROJECT, MATCH_END, MATCH_START,
+    },
     editor::{Content, Editor},
+    history::{Change, History},
     preview::Preview,
+    project_picker::{ProjectAction, ProjectPicker},
+    prompt::Prompt,
+    report::format_duration,
+    revision_view::RevisionView,
+    store::TaskStore,
 };
 
+#[derive(Clone)]
 enum Direction {
     Up,
     Down,
 }
 
+#[derive(PartialEq)]
+enum View {
+    Tasks,
+    Trash,
+    Archive,
+}
+
 enum EditType {
     Editing,
     Adding,
     Done,
 }
 
+enum PromptType {
+    Search,
+    Tags,
+    NewProject,
+    RenameProject(i64),
+    Recurrence,
+    Field,
+    FieldFilter,
+    SortField,
+    Done,
+}
+
+enum PickType {
+    Switch,
+    Move,
+    Done,
+}
+
+const TICK: Duration = Duration::from_secs(1);
+
+const TAG_COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::LightRed,
+];
+
+type Progress = HashMap<i64, (usize, usize)>;
+
+fn tree(tasks: Vec<Task>, collapsed: &HashSet<i64>) -> (Vec<Task>, Vec<usize>, Progress) {
+    let ids: HashSet<i64> = tasks.iter().map(|task| task.id).collect();
+    let mut children: HashMap<Option<i64>, Vec<Task>> = HashMap::new();
+    for task in tasks {
+        let parent_id = task.parent_id.filter(|id| ids.contains(id));
+        children.entry(parent_id).or_default().push(task);
+    }
+
+    let progress = ids
+        .iter()
+        .filter(|id| children.contains_key(&Some(**id)))
+        .map(|id| (*id, count(&children, *id)))
+        .collect();
+    let mut tasks = vec![];
+    let mut depths = vec![];
+    walk(&mut children, None, 0, collapsed, &mut tasks, &mut depths);
+
+    (tasks, depths, progress)
+}
+
+fn count(children: &HashMap<Option<i64>, Vec<Task>>, id: i64) -> (usize, usize) {
+    let mut progress = (0, 0);
+    for child in children.get(&Some(id)).into_iter().flatten() {
+        let (done, total) = count(children, child.id);
+        progress.0 += done + child.done as usize;
+        progress.1 += total + 1;
+    }
+    progress
+}
+
+fn walk(
+    children: &mut HashMap<Option<i64>, Vec<Task>>,
+    parent_id: Option<i64>,
+    depth: usize,
+    collapsed: &HashSet<i64>,
+    tasks: &mut Vec<Task>,
+    depths: &mut Vec<usize>,
+) {
+    for task in children.remove(&parent_id).unwrap_or_default() {
+        let id = task.id;
+        tasks.push(task);
+        depths.push(depth);
+        if !collapsed.contains(&id) {
+            walk(children, Some(id), depth + 1, collapsed, tasks, depths);
+        }
+    }
+}
+
+fn tag_color(tag: &str) -> Color {
+    let hash = tag
+        .to_lowercase()
+        .bytes()
+        .fold(0usize, |hash, byte| hash.wrapping_mul(31).wrapping_add(byte.into()));
+    TAG_COLORS[hash % TAG_COLORS.len()]
+}
+
+// name=value terms separated by spaces; quote a value to keep its spaces, e.g. customer="ACME corp"
+fn parse_fields(input: &str) -> std::result::Result<Vec<Field>, String> {
+    let mut terms = vec![];
+    let mut term = String::new();
+    let mut quoted = false;
+    for c in input.chars() {
+        if c.is_whitespace() && !quoted {
+            terms.extend((!term.is_empty()).then(|| mem::take(&mut term)));
+            continue;
+        }
+        quoted ^= c == '"';
+        term.push(c);
+    }
+    if quoted {
+        return Err(format!("unclosed quote in {}", term));
+    }
+    terms.extend((!term.is_empty()).then_some(term));
+
+    terms
+        .iter()
+        .map(|term| match term.split_once('=') {
+            Some((name, value)) if !name.is_empty() => Ok(Field {
+                name: name.to_string(),
+                value: FieldValue::parse(value),
+            }),
+            _ => Err(format!("expected name=value, got {}", term)),
+        })
+        .collect()
+}
+
+// the inverse of parse_fields, quoting text that would otherwise split or parse as another kind
+fn format_field(field: &Field) -> String {
+    match &field.value {
+        FieldValue::Text(text) if text.contains(char::is_whitespace) || FieldValue::parse(text) != field.value => {
+            format!("{}=\"{}\"", field.name, text)
+        }
+        value => format!("{}={}", field.name, value),
+    }
+}
+
 pub struct Todo<'a> {
-    db: Db,
+    store: Box<dyn TaskStore>,
     editor: Editor<'a>,
     preview: Preview,
     date_picker: DatePicker,
+    prompt: Prompt<'a>,
+    project_picker: ProjectPicker,
+    revision_view: RevisionView,
+    history: History,
+    project: Project,
     tasks: Vec<Task>,
+    depths: Vec<usize>,
+    progress: Progress,
+    collapsed: HashSet<i64>,
+    blocker: Option<Task>,
+    timer: Option<TimeEntry>,
+    search: Option<String>,
+    hits: Vec<SearchHit>,
+    tags: Vec<String>,
+    fields: Vec<Field>,
+    sort: Sort,
+    sort_field: Option<String>,
+    view: View,
     current: usize,
     direction: Direction,
     scroll: RefCell<usize>,
     edit_type: EditType,
+    prompt_type: PromptType,
+    pick_type: PickType,
     layout_direction: LayoutDirection,
+    message: Option<String>,
+    data_version: i64,
     exit: bool,
 }
 
 impl Todo<'_> {
-    pub fn new() -> Self {
+    pub fn new(store: Box<dyn TaskStore>) -> Self {
+        let project = store.get_project(DEFAULT_PROJECT).ok().flatten().unwrap_or(Project {
+            id: DEFAULT_PROJECT,
+            name: String::from("todo"),
+            archived: false,
+        });
         let mut todo = Self {
-            db: Db::new(),
+            store,
             editor: Editor::new(),
             preview: Preview::new(),
             date_picker: DatePicker::new(),
+            prompt: Prompt::new(),
+            project_picker: ProjectPicker::new(),
+            revision_view: RevisionView::new(),
+            history: History::new(),
+            project,
             tasks: vec![],
+            depths: vec![],
+            progress: HashMap::new(),
+            collapsed: HashSet::new(),
+            blocker: None,
+            timer: None,
+            search: None,
+            hits: vec![],
+            tags: vec![],
+            fields: vec![],
+            sort: Sort::Position,
+            sort_field: None,
+            view: View::Tasks,
             current: 0,
             direction: Direction::Down,
             scroll: RefCell::new(0),
             edit_type: EditType::Done,
+            prompt_type: PromptType::Done,
+            pick_type: PickType::Done,
             layout_direction: LayoutDirection::Horizontal,
+            message: None,
+            data_version: 0,
             exit: false,
         };
 
+        let result = todo.store.data_version();
+        todo.data_version = todo.check(result).unwrap_or_default();
         todo.update();
 
         todo
@@ -98,41 +294,101 @@ impl Todo<'_> {
 
         frame.render_widget(&self.editor, frame.area());
         frame.render_widget(&self.date_picker, frame.area());
+        frame.render_widget(&self.project_picker, frame.area());
+        frame.render_widget(&self.revision_view, frame.area());
+        frame.render_widget(&self.prompt, frame.area());
     }
 
     fn handle_events(&mut self) -> io::Result<()> {
-        match event::read()? {
-            Event::Key(key_event) => {
-                if key_event.kind == KeyEventKind::Press {
-                    if self.date_picker.handle_key_press_event(key_event) {
-                        if let Some(date) = self.date_picker.get_date() {
-                            self.update_due(date);
+        if !event::poll(TICK)? {
+            self.refresh_if_changed();
+            if self.timer.is_some() {
+                self.update_preview();
+            }
+            return Ok(());
+        }
+
+        if let Event::Key(key_event) = event::read()? {
+            if key_event.kind == KeyEventKind::Press {
+                if self.date_picker.handle_key_press_event(key_event) {
+                    if let Some(date) = self.date_picker.get_date() {
+                        self.update_due(date);
+                    }
+                } else if self.editor.handle_key_press_event(key_event) {
+                    if let Some(content) = self.editor.get_content() {
+                        match self.edit_type {
+                            EditType::Editing => {
+                                self.update_current(content);
+                            }
+                            EditType::Adding => {
+                                self.add_task(content);
+                            }
+                            _ => (),
                         }
-                    } else if self.editor.handle_key_press_event(key_event) {
-                        if let Some(content) = self.editor.get_content() {
-                            match self.edit_type {
-                                EditType::Editing => {
-                                    self.update_current(content);
-                                }
-                                EditType::Adding => {
-                                    self.add_task(content);
+                        self.edit_type = EditType::Done;
+                    }
+                } else if self.prompt.handle_key_press_event(key_event) {
+                    if let Some(input) = self.prompt.get_input() {
+                        match self.prompt_type {
+                            PromptType::Search => {
+                                self.search(input);
+                            }
+                            PromptType::Tags => {
+                                self.filter_tags(input);
+                            }
+                            PromptType::NewProject => {
+                                self.create_project(input);
+                            }
+                            PromptType::RenameProject(id) => {
+                                self.rename_project(id, input);
+                            }
+                            PromptType::Recurrence => {
+                                self.set_recurrence(input);
+                            }
+                            PromptType::Field => {
+                                self.set_field(input);
+                            }
+                            PromptType::FieldFilter => {
+                                self.filter_fields(input);
+                            }
+                            PromptType::SortField => {
+                                self.sort_by_field(input);
+                            }
+                            PromptType::Done => (),
+                        }
+                        self.prompt_type = PromptType::Done;
+                    }
+                } else if self.project_picker.handle_key_press_event(key_event) {
+                    if let Some(action) = self.project_picker.get_action() {
+                        match self.pick_type {
+                            PickType::Switch => {
+                                self.handle_project_action(action);
+                            }
+                            PickType::Move => {
+                                if let ProjectAction::Select(id) = action {
+                                    self.move_current(id);
                                 }
-                                _ => (),
                             }
-                            self.edit_type = EditType::Done;
+                            PickType::Done => (),
                         }
-                    } else {
-                        self.handle_key_press_event(key_event);
+                        self.pick_type = PickType::Done;
+                    }
+                } else if self.revision_view.handle_key_press_event(key_event) {
+                    if let Some(revision) = self.revision_view.get_revision() {
+                        self.revert_current(revision);
                     }
+                } else {
+                    self.handle_key_press_event(key_event);
                 }
             }
-            _ => (),
-        };
+        }
 
         Ok(())
     }
 
     fn handle_key_press_event(&mut self, key_event: KeyEvent) {
+        self.message = None;
+
         match key_event.code {
             KeyCode::F(1) => {
                 self.exit = true;
@@ -142,10 +398,6 @@ impl Todo<'_> {
                 self.direction = Direction::Up;
                 self.update_preview();
             }
-            KeyCode::Char('K') => {
-                self.switch(Direction::Up);
-                self.direction = Direction::Up;
-            }
             KeyCode::Char('j') => {
                 if self.current < self.tasks.len().saturating_sub(1) {
                     self.current += 1;
@@ -153,6 +405,35 @@ impl Todo<'_> {
                 self.direction = Direction::Down;
                 self.update_preview();
             }
+            KeyCode::Char('T') => {
+                self.toggle_view(View::Trash);
+            }
+            KeyCode::Char('X') => {
+                self.toggle_view(View::Archive);
+            }
+            KeyCode::Char('p') => {
+                self.pick_project();
+            }
+            KeyCode::Char('u') => {
+                self.undo();
+            }
+            KeyCode::Char('r') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.redo();
+            }
+            _ => match self.view {
+                View::Tasks => self.handle_tasks_key_press_event(key_event),
+                View::Trash => self.handle_trash_key_press_event(key_event),
+                View::Archive => self.handle_archive_key_press_event(key_event),
+            },
+        };
+    }
+
+    fn handle_tasks_key_press_event(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Char('K') => {
+                self.switch(Direction::Up);
+                self.direction = Direction::Up;
+            }
             KeyCode::Char('J') => {
                 self.switch(Direction::Down);
                 self.direction = Direction::Down;
@@ -160,12 +441,12 @@ impl Todo<'_> {
             KeyCode::Char('a') => {
                 if let Some(task) = self.tasks.get(self.current) {
                     self.edit_type = EditType::Editing;
-                    self.editor.start(&task.subject, &task.body, task.done);
+                    self.editor.start(&task.subject, &task.body, &task.tags, task.done);
                 }
             }
             KeyCode::Char('A') => {
                 self.edit_type = EditType::Adding;
-                self.editor.start("", "", false);
+                self.editor.start("", "", &[], false);
             }
             KeyCode::Char('s') => {
                 self.pick_date();
@@ -177,91 +458,728 @@ impl Todo<'_> {
                 self.done_current();
             }
             KeyCode::Char('D') => {
-                self.delete_current();
+                self.trash_current();
+            }
+            KeyCode::Char('x') => {
+                self.archive_completed();
+            }
+            KeyCode::Char('h') => {
+                self.collapse_current();
+            }
+            KeyCode::Char('l') => {
+                self.expand_current();
+            }
+            KeyCode::Char('>') => {
+                self.indent_current();
+            }
+            KeyCode::Char('<') => {
+                self.outdent_current();
+            }
+            KeyCode::Char('m') if !self.tasks.is_empty() => {
+                self.pick_move_target();
+            }
+            KeyCode::Char('/') => {
+                self.prompt_type = PromptType::Search;
+                let query = self.search.clone().unwrap_or_default();
+                self.prompt.start("search", &query);
+            }
+            KeyCode::Char('#') => {
+                self.prompt_type = PromptType::Tags;
+                let tags = self
+                    .tags
+                    .iter()
+                    .map(|tag| format!("#{}", tag))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                self.prompt.start("filter tags", &tags);
+            }
+            KeyCode::Char('+') => {
+                self.modify_current(|task| task.priority = task.priority.raise());
+            }
+            KeyCode::Char('-') => {
+                self.modify_current(|task| task.priority = task.priority.lower());
+            }
+            KeyCode::Char('o') => {
+                self.sort = self.sort.next();
+                self.update();
+            }
+            KeyCode::Char('O') => {
+                self.prompt_type = PromptType::SortField;
+                let name = self.sort_field.clone().unwrap_or_default();
+                self.prompt.start("sort by field", &name);
+            }
+            KeyCode::Char('f') if !self.tasks.is_empty() => {
+                self.prompt_type = PromptType::Field;
+                self.prompt.start("set field (name=value, or name= to remove)", "");
+            }
+            KeyCode::Char('F') => {
+                self.prompt_type = PromptType::FieldFilter;
+                let fields = self.fields.iter().map(format_field).collect::<Vec<_>>().join(" ");
+                self.prompt.start("filter fields (name=value ...)", &fields);
+            }
+            KeyCode::Char('r') => {
+                if let Some(task) = self.tasks.get(self.current) {
+                    let rule = task.recurrence.as_ref().map(|r| r.to_string()).unwrap_or_default();
+                    self.prompt_type = PromptType::Recurrence;
+                    self.prompt.start("repeat (RRULE)", &rule);
+                }
+            }
+            KeyCode::Char('t') => {
+                self.toggle_timer();
+            }
+            KeyCode::Char('H') => {
+                self.show_revisions();
+            }
+            KeyCode::Char('b') => {
+                self.block_current();
+            }
+            KeyCode::Char('B') => {
+                self.modify_current(|task| task.blockers = vec![]);
+            }
+            KeyCode::Esc if self.blocker.is_some() => {
+                self.blocker = None;
+            }
+            KeyCode::Esc if self.search.is_some() || !self.tags.is_empty() || !self.fields.is_empty() => {
+                self.search = None;
+                self.tags = vec![];
+                self.fields = vec![];
+                self.current = 0;
+                self.update();
+            }
+            _ => (),
+        };
+    }
+
+    fn handle_trash_key_press_event(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Char('r') => {
+                self.restore_current();
+            }
+            KeyCode::Char('D') => {
+                self.purge_current();
             }
             _ => (),
         };
     }
 
+    fn handle_archive_key_press_event(&mut self, key_event: KeyEvent) {
+        if let KeyCode::Char('r') = key_event.code {
+            self.unarchive_current();
+        }
+    }
+
     fn update_current(&mut self, content: Content) {
-        if let Some(task) = self.tasks.get_mut(self.current) {
+        self.modify_current(|task| {
             task.subject = content.subject;
             task.body = content.body;
-            self.db.update_one(task);
-            self.update();
+            task.tags = content.tags;
+        });
+    }
+
+    fn show_revisions(&mut self) {
+        if let Some(task) = self.tasks.get(self.current).cloned() {
+            let result = self.store.revisions(task.id);
+            match self.check(result) {
+                Some(revisions) if revisions.is_empty() => {
+                    self.message = Some(String::from("no earlier revisions"));
+                }
+                Some(revisions) => self.revision_view.start(&task, revisions),
+                None => (),
+            }
+        }
+    }
+
+    fn revert_current(&mut self, revision: Revision) {
+        if self.tasks.get(self.current).is_some_and(|task| task.id == revision.task_id) {
+            self.modify_current(|task| {
+                task.subject = revision.subject;
+                task.body = revision.body;
+            });
         }
     }
 
     fn done_current(&mut self) {
-        if let Some(task) = self.tasks.get_mut(self.current) {
-            task.done = !task.done;
-            self.db.update_one(task);
+        match self.tasks.get(self.current) {
+            Some(task) if !task.done && task.recurrence.is_some() => {
+                let task = task.clone();
+                self.complete_recurring(task);
+            }
+            _ => self.modify_current(|task| task.done = !task.done),
+        };
+
+        if let Some(task) = self.tasks.get(self.current).filter(|task| task.done) {
+            let result = self.store.dependents(task.id);
+            if let Some(dependents) = self.check(result) {
+                let unblocked: Vec<_> = dependents
+                    .into_iter()
+                    .filter(|task| !task.blocked)
+                    .map(|task| task.subject)
+                    .collect();
+                if !unblocked.is_empty() {
+                    self.message = Some(format!("unblocked: {}", unblocked.join(", ")));
+                }
+            }
+        }
+    }
+
+    fn complete_recurring(&mut self, before: Task) {
+        let mut task = before.clone();
+        task.done = true;
+        let result = self.store.update_one(&task).and_then(|after| match after {
+            Some(after) => {
+                let next = self.store.spawn_next(&after, Local::now().date_naive())?;
+                Ok(Some((after, next)))
+            }
+            None => Ok(None),
+        });
+        if let Some(Some((after, next))) = self.check(result) {
+            let mut changes = vec![Change::Update {
+                before: Box::new(before),
+                after: Box::new(after),
+            }];
+            if let Some(next) = next {
+                if let Some(due) = next.due {
+                    self.message = Some(format!("next due {}", due.format("%Y/%m/%d")));
+                }
+                changes.push(Change::Insert(next));
+            }
+            self.history.record(Change::Group(changes));
+        }
+        self.update();
+        self.select(task.id);
+    }
+
+    fn set_recurrence(&mut self, input: String) {
+        let recurrence = if input.trim().is_empty() {
+            None
+        } else {
+            match input.parse() {
+                Ok(recurrence) => Some(recurrence),
+                Err(error) => {
+                    self.message = Some(error);
+                    return;
+                }
+            }
+        };
+        self.modify_current(|task| task.recurrence = recurrence);
+    }
+
+    fn set_field(&mut self, input: String) {
+        let (name, value) = match input.split_once('=') {
+            Some((name, value)) if !name.trim().is_empty() => (name.trim(), value),
+            _ => {
+                self.message = Some(format!("expected name=value, got {}", input.trim()));
+                return;
+            }
+        };
+        let value = (!value.trim().is_empty()).then(|| FieldValue::parse(value));
+        self.modify_current(|task| task.set_field(name, value));
+    }
+
+    fn filter_fields(&mut self, input: String) {
+        match parse_fields(&input) {
+            Ok(fields) => self.fields = fields,
+            Err(error) => {
+                self.message = Some(error);
+                return;
+            }
+        }
+        self.current = 0;
+        self.update();
+    }
+
+    fn sort_by_field(&mut self, input: String) {
+        let name = input.trim();
+        self.sort_field = if name.is_empty() {
+            None
+        } else {
+            Some(name.to_string())
+        };
+        self.update();
+    }
+
+    fn toggle_timer(&mut self) {
+        if let Some(task) = self.tasks.get(self.current) {
+            let result = match &self.timer {
+                Some(timer) if timer.task_id == task.id => self.store.stop_timer().map(|_| None),
+                _ => self.store.start_timer(task.id).map(Some),
+            };
+            if let Some(timer) = self.check(result) {
+                self.timer = timer;
+            }
+            self.update_preview();
+        }
+    }
+
+    fn block_current(&mut self) {
+        match self.blocker.take() {
+            Some(blocker) => {
+                self.modify_current(|task| task.blockers.push(blocker.id));
+            }
+            None => {
+                self.blocker = self.tasks.get(self.current).cloned();
+            }
+        }
+    }
+
+    fn modify_current<F>(&mut self, modify: F)
+    where
+        F: FnOnce(&mut Task),
+    {
+        if let Some(task) = self.tasks.get(self.current) {
+            let before = task.clone();
+            let mut task = task.clone();
+            modify(&mut task);
+            let result = self.store.update_one(&task);
+            if let Some(Some(after)) = self.check(result) {
+                self.history.record(Change::Update {
+                    before: Box::new(before),
+                    after: Box::new(after),
+                });
+            }
+            self.update();
+            self.select(task.id);
+        }
+    }
+
+    fn select(&mut self, id: i64) {
+        if let Some(index) = self.tasks.iter().position(|task| task.id == id) {
+            self.current = index;
+            self.update_preview();
+        }
+    }
+
+    fn sibling(&self, direction: Direction) -> Option<usize> {
+        let depth = *self.depths.get(self.current)?;
+        let mut indices: Box<dyn Iterator<Item = usize>> = match direction {
+            Direction::Up => Box::new((0..self.current).rev()),
+            Direction::Down => Box::new(self.current + 1..self.tasks.len()),
+        };
+        indices
+            .find(|i| self.depths[*i] <= depth)
+            .filter(|i| self.depths[*i] == depth)
+    }
+
+    fn collapse_current(&mut self) {
+        if let Some(task) = self.tasks.get(self.current) {
+            if self.progress.contains_key(&task.id) && self.collapsed.insert(task.id) {
+                self.update();
+            } else if let Some(parent_id) = task.parent_id {
+                self.select(parent_id);
+            }
+        }
+    }
+
+    fn expand_current(&mut self) {
+        if let Some(task) = self.tasks.get(self.current) {
+            if self.collapsed.remove(&task.id) {
+                self.update();
+            } else if self.progress.contains_key(&task.id) {
+                self.current += 1;
+                self.update_preview();
+            }
+        }
+    }
+
+    fn indent_current(&mut self) {
+        if let Some(index) = self.sibling(Direction::Up) {
+            let parent_id = self.tasks[index].id;
+            self.collapsed.remove(&parent_id);
+            self.modify_current(|task| task.parent_id = Some(parent_id));
+        }
+    }
+
+    fn outdent_current(&mut self) {
+        let parent_id = match self.tasks.get(self.current) {
+            Some(task) if self.depths[self.current] > 0 => task.parent_id,
+            _ => return,
+        };
+        let parent_id = self
+            .tasks
+            .iter()
+            .find(|task| Some(task.id) == parent_id)
+            .and_then(|task| task.parent_id);
+        self.modify_current(|task| task.parent_id = parent_id);
+    }
+
+    fn trash_current(&mut self) {
+        if let Some(task) = self.tasks.get(self.current) {
+            let result = self.store.trash_one(task.id);
+            if let Some(Some(task)) = self.check(result) {
+                self.history.record(Change::Trash(task));
+            }
+            self.update();
+        }
+    }
+
+    fn restore_current(&mut self) {
+        if let Some(task) = self.tasks.get(self.current) {
+            let result = self.store.restore_one(task.id);
+            if let Some(Some(task)) = self.check(result) {
+                self.history.record(Change::Restore(task));
+            }
+            self.update();
+        }
+    }
+
+    fn purge_current(&mut self) {
+        if let Some(task) = self.tasks.get(self.current) {
+            let result = self.store.purge_one(task.id);
+            if let Some(Some(task)) = self.check(result) {
+                self.history.record(Change::Purge(task));
+            }
             self.update();
         }
     }
 
-    fn delete_current(&mut self) {
-        if let Some(task) = self.tasks.get_mut(self.current) {
-            self.db.delete_one(task.id);
+    fn archive_completed(&mut self) {
+        let result = self.store.archive_done(Some(self.project.id), 0);
+        match self.check(result) {
+            Some(tasks) if tasks.is_empty() => {
+                self.message = Some(String::from("no completed tasks to archive"));
+            }
+            Some(tasks) => {
+                self.message = Some(format!("archived {} completed tasks", tasks.len()));
+                self.history.record(Change::Group(tasks.into_iter().map(Change::Archive).collect()));
+            }
+            None => (),
+        }
+        self.update();
+    }
+
+    fn unarchive_current(&mut self) {
+        if let Some(task) = self.tasks.get(self.current) {
+            let result = self.store.unarchive_one(task.id);
+            if let Some(Some(task)) = self.check(result) {
+                self.history.record(Change::Unarchive(task));
+            }
             self.update();
         }
     }
 
+    fn move_current(&mut self, project_id: i64) {
+        self.modify_current(|task| task.project_id = project_id);
+    }
+
+    fn pick_project(&mut self) {
+        let result = self.store.list_projects();
+        if let Some(projects) = self.check(result) {
+            self.pick_type = PickType::Switch;
+            self.project_picker.start("projects", projects, self.project.id, true);
+        }
+    }
+
+    fn pick_move_target(&mut self) {
+        let result = self.store.list_projects();
+        if let Some(projects) = self.check(result) {
+            let projects = projects.into_iter().filter(|p| !p.archived).collect();
+            self.pick_type = PickType::Move;
+            self.project_picker.start("move to", projects, self.project.id, false);
+        }
+    }
+
+    fn handle_project_action(&mut self, action: ProjectAction) {
+        match action {
+            ProjectAction::Select(id) => {
+                self.switch_project(id);
+            }
+            ProjectAction::Create => {
+                self.prompt_type = PromptType::NewProject;
+                self.prompt.start("new project", "");
+            }
+            ProjectAction::Rename(id) => {
+                let result = self.store.get_project(id);
+                if let Some(Some(project)) = self.check(result) {
+                    self.prompt_type = PromptType::RenameProject(id);
+                    self.prompt.start("rename project", &project.name);
+                }
+            }
+            ProjectAction::Archive(id) => {
+                let result = self
+                    .store
+                    .get_project(id)
+                    .and_then(|project| match project {
+                        Some(project) => self.store.archive_project(id, !project.archived),
+                        None => Ok(None),
+                    });
+                if let Some(Some(project)) = self.check(result) {
+                    if project.id == self.project.id {
+                        self.project = project;
+                    }
+                }
+                self.pick_project();
+            }
+            ProjectAction::Delete(id) => {
+                let result = self.store.delete_project(id);
+                if self.check(result).is_some() && id == self.project.id {
+                    self.switch_project(DEFAULT_PROJECT);
+                }
+                self.pick_project();
+            }
+        };
+    }
+
+    fn refresh_if_changed(&mut self) {
+        let result = self.store.data_version();
+        match self.check(result) {
+            Some(version) if version != self.data_version => {
+                self.data_version = version;
+                self.refresh();
+            }
+            _ => (),
+        }
+    }
+
+    fn refresh(&mut self) {
+        let result = self.store.get_project(self.project.id);
+        match self.check(result) {
+            Some(Some(project)) => self.project = project,
+            Some(None) => return self.switch_project(DEFAULT_PROJECT),
+            None => (),
+        }
+
+        let id = self.tasks.get(self.current).map(|task| task.id);
+        self.update();
+        if let Some(id) = id {
+            self.select(id);
+        }
+    }
+
+    fn switch_project(&mut self, id: i64) {
+        let result = self.store.get_project(id);
+        if let Some(Some(project)) = self.check(result) {
+            self.project = project;
+            self.view = View::Tasks;
+            self.search = None;
+            self.tags = vec![];
+            self.fields = vec![];
+            self.current = 0;
+            self.update();
+        }
+    }
+
+    fn create_project(&mut self, name: String) {
+        let name = name.trim();
+        if name.is_empty() {
+            return;
+        }
+        let result = self.store.create_project(name);
+        if let Some(project) = self.check(result) {
+            self.switch_project(project.id);
+        }
+    }
+
+    fn rename_project(&mut self, id: i64, name: String) {
+        let name = name.trim();
+        if name.is_empty() {
+            return;
+        }
+        let result = self.store.rename_project(id, name);
+        if let Some(Some(project)) = self.check(result) {
+            if project.id == self.project.id {
+                self.project = project;
+            }
+        }
+    }
+
+    fn toggle_view(&mut self, view: View) {
+        self.view = if self.view == view { View::Tasks } else { view };
+        self.current = 0;
+        self.update();
+    }
+
     fn add_task(&mut self, content: Content) {
-        if let Some(_) = self.db.insert_one(&content.subject, &content.body) {
-            self.current = self.tasks.len();
+        let result = self.store.insert_one(self.project.id, &content.subject, &content.body).and_then(|task| {
+            match task {
+                Some(mut task) if !content.tags.is_empty() => {
+                    task.tags = content.tags;
+                    self.store.update_one(&task)
+                }
+                task => Ok(task),
+            }
+        });
+        if let Some(Some(task)) = self.check(result) {
+            let id = task.id;
+            self.history.record(Change::Insert(task));
             self.update();
+            self.select(id);
         }
     }
 
     fn switch(&mut self, direction: Direction) {
-        if let Some(current) = self.tasks.get_mut(self.current) {
-            if let Some(mut next) = match direction {
-                Direction::Up => self.db.get_prev(current.id),
-                Direction::Down => self.db.get_next(current.id),
-            } {
-                mem::swap(&mut current.id, &mut next.id);
-                self.db.update_one(&current);
-                self.db.update_one(&next);
-                self.current = match direction {
-                    Direction::Up => self.current.saturating_sub(1),
-                    Direction::Down => self.current + 1,
-                };
-                self.update();
-            }
+        if let Some(name) = &self.sort_field {
+            self.message = Some(format!("tasks are sorted by {}", name));
+            return;
+        }
+        if self.sort != Sort::Position {
+            self.message = Some(format!("tasks are sorted by {}", self.sort.name()));
+            return;
+        }
+        let index = match self.sibling(direction.clone()) {
+            Some(index) => index,
+            None => return,
+        };
+        let id = self.tasks[self.current].id;
+        let other = self.tasks[index].id;
+        let result = match direction {
+            Direction::Up => self.store.move_before(id, other),
+            Direction::Down => self.store.move_after(id, other),
+        };
+        if let Some(Some((from, to))) = self.check(result) {
+            self.history.record(Change::Move { id, from, to });
+        }
+        self.update();
+        self.select(id);
+    }
+
+    fn undo(&mut self) {
+        let result = self.history.undo(self.store.as_ref());
+        if let Some(false) = self.check(result) {
+            self.message = Some(String::from("nothing to undo"));
+        }
+        self.update();
+    }
+
+    fn redo(&mut self) {
+        let result = self.history.redo(self.store.as_ref());
+        if let Some(false) = self.check(result) {
+            self.message = Some(String::from("nothing to redo"));
         }
+        self.update();
     }
 
     fn pick_date(&mut self) {
         if let Some(task) = self.tasks.get(self.current) {
-            self.date_picker.start(&task.due);
+            self.date_picker.start(task.due);
         }
     }
 
     fn clear_date(&mut self) {
-        if let Some(task) = self.tasks.get_mut(self.current) {
-            task.due = String::new();
-            self.db.update_one(task);
-        }
+        self.modify_current(|task| task.due = None);
     }
 
-    fn update_due(&mut self, date: String) {
-        if let Some(task) = self.tasks.get_mut(self.current) {
-            task.due = date;
-            self.db.update_one(task);
-        }
+    fn update_due(&mut self, date: NaiveDate) {
+        self.modify_current(|task| task.due = Some(date));
+    }
+
+    fn search(&mut self, query: String) {
+        let query = query.trim();
+        self.search = if query.is_empty() {
+            None
+        } else {
+            Some(query.to_string())
+        };
+        self.current = 0;
+        self.update();
+    }
+
+    fn filter_tags(&mut self, input: String) {
+        self.tags = input
+            .split_whitespace()
+            .map(|tag| tag.trim_start_matches('#').to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        self.current = 0;
+        self.update();
     }
 
     fn update_preview(&mut self) {
-        if let Some(task) = self.tasks.get(self.current) {
+        if let Some(hit) = self.hits.get(self.current) {
+            self.preview.show(&hit.subject, &hit.body);
+        } else if let Some(task) = self.tasks.get(self.current) {
             self.preview.show(&task.subject, &task.body);
         } else {
-            self.preview.show(&String::new(), &String::new());
+            self.preview.show("", "");
+        }
+
+        let format = |datetime: DateTime<Utc>| {
+            datetime.with_timezone(&Local).format("%Y/%m/%d %H:%M").to_string()
+        };
+        let id = match self.tasks.get(self.current) {
+            Some(task) => {
+                let mut details: Vec<_> = task
+                    .fields
+                    .iter()
+                    .map(|field| format!("{}: {}", field.name, field.value))
+                    .collect();
+                details.extend([
+                    format!("created {}", format(task.created)),
+                    format!("updated {}", format(task.updated_at)),
+                ]);
+                if let Some(completed_at) = task.completed_at {
+                    details.push(format!("completed {}", format(completed_at)));
+                }
+                details.push(format!("uuid {}", task.uuid));
+                self.preview.show_details(details);
+                task.id
+            }
+            None => {
+                self.preview.show_details(vec![]);
+                return self.preview.show_time(None);
+            }
+        };
+        let result = self.store.tracked(id);
+        let total = self.check(result).unwrap_or(0);
+        let time = match &self.timer {
+            Some(timer) if timer.task_id == id => {
+                let running = (Utc::now() - timer.started_at).num_seconds();
+                Some(format!(
+                    "● {} · {} total",
+                    format_duration(running),
+                    format_duration(total),
+                ))
+            }
+            _ if total > 0 => Some(format!("{} tracked", format_duration(total))),
+            _ => None,
+        };
+        self.preview.show_time(time);
+    }
+
+    fn check<T>(&mut self, result: Result<T>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.message = Some(error.to_string());
+                None
+            }
         }
     }
 
     fn update(&mut self) {
-        self.tasks = self.db.list();
+        let result = self.store.running_timer();
+        if let Some(timer) = self.check(result) {
+            self.timer = timer;
+        }
+
+        self.hits = vec![];
+        self.progress = HashMap::new();
+        let mut query = TaskQuery::new()
+            .project(self.project.id)
+            .tags(&self.tags)
+            .sort(self.sort);
+        for field in &self.fields {
+            query = query.field(&field.name, field.value.clone());
+        }
+        if let Some(name) = &self.sort_field {
+            query = query.sort_field(name);
+        }
+        let result = match (&self.view, &self.search) {
+            (View::Tasks, Some(text)) => self.store.search(&query.text(text)).map(|hits| {
+                self.hits = hits;
+                self.hits.iter().map(|hit| hit.task.clone()).collect()
+            }),
+            (View::Tasks, None) => self.store.query(&query),
+            (View::Trash, _) => self.store.list_trash(),
+            (View::Archive, _) => self.store.list_archive(self.project.id),
+        };
+        if let Some(tasks) = self.check(result) {
+            if self.view == View::Tasks && self.search.is_none() && self.tags.is_empty() && self.fields.is_empty() {
+                (self.tasks, self.depths, self.progress) = tree(tasks, &self.collapsed);
+            } else {
+                self.depths = vec![0; tasks.len()];
+                self.tasks = tasks;
+            }
+        }
 
         if self.current >= self.tasks.len() {
             self.current = self.tasks.len().saturating_sub(1);
@@ -301,40 +1219,103 @@ impl Widget for &Todo<'_> {
             }
         };
 
-        let mut done = false;
         let from = cmp::min(*scroll, len.saturating_sub(1));
         let to = cmp::min(*scroll + height, len);
         let lines: Vec<_> = self.tasks[from..to]
             .iter()
             .enumerate()
             .map(|(i, task)| {
-                if i == self.current.saturating_sub(*scroll) {
-                    let string = format!(
-                        "{:>10} {:<width$}",
-                        task.due,
+                let due = match task.due {
+                    Some(due) => due.format("%Y/%m/%d").to_string(),
+                    None => String::new(),
+                };
+                let subject = match self.hits.get(from + i) {
+                    Some(hit) if hit.snippet.contains(MATCH_START) => format!(
+                        "{} {}",
                         task.subject,
-                        width = area.width.into(),
-                    );
-                    if task.done {
-                        done = true;
-                        Line::from(string.black().on_red())
-                    } else {
-                        Line::from(string.white().on_red())
-                    }
+                        hit.snippet.replace([MATCH_START, MATCH_END], ""),
+                    ),
+                    _ => task.subject.clone(),
+                };
+                let subject = if task.blocked && !task.done {
+                    format!("⊘ {}", subject)
                 } else {
-                    let subject = format!("{:>10} {}", task.due, task.subject);
-                    if task.done {
-                        Line::from(subject.red())
-                    } else {
-                        Line::from(subject)
-                    }
+                    subject
+                };
+                let subject = if task.recurrence.is_some() {
+                    format!("{} ↻", subject)
+                } else {
+                    subject
+                };
+                let depth = self.depths.get(from + i).copied().unwrap_or(0);
+                let subject = match self.progress.get(&task.id) {
+                    Some((done, total)) => format!(
+                        "{}{} {} [{}/{}]",
+                        "  ".repeat(depth),
+                        if self.collapsed.contains(&task.id) { "▸" } else { "▾" },
+                        subject,
+                        done,
+                        total,
+                    ),
+                    None => format!("{}{}", "  ".repeat(depth), subject),
+                };
+                let selected = i == self.current.saturating_sub(*scroll);
+                let style = match (selected, task.done) {
+                    (true, true) => Style::new().black().on_red(),
+                    (true, false) => Style::new().white().on_red(),
+                    (false, true) => Style::new().red(),
+                    (false, false) if task.blocked => Style::new().dark_gray(),
+                    (false, false) => Style::new(),
+                };
+
+                let mut spans = vec![Span::styled(
+                    format!("{:>10} {} {}", due, task.priority.symbol(), subject),
+                    style,
+                )];
+                for tag in &task.tags {
+                    spans.push(Span::styled(" ", style));
+                    spans.push(Span::raw(format!(" {} ", tag)).black().bg(tag_color(tag)));
+                }
+                if selected {
+                    let width: usize = spans.iter().map(Span::width).sum();
+                    let padding = " ".repeat((area.width as usize).saturating_sub(width));
+                    spans.push(Span::styled(padding, style));
                 }
+                Line::from(spans)
             })
             .collect();
 
-        let block = Block::new()
+        let mut title = match self.view {
+            View::Tasks => format!(" {} ", self.project.name),
+            View::Trash => String::from(" trash "),
+            View::Archive => format!(" {} archive ", self.project.name),
+        };
+        if let View::Tasks = self.view {
+            if let Some(query) = &self.search {
+                title += &format!("/{} ", query);
+            }
+            for tag in &self.tags {
+                title += &format!("#{} ", tag);
+            }
+            for field in &self.fields {
+                title += &format!("{}={} ", field.name, field.value);
+            }
+            if let Some(name) = &self.sort_field {
+                title += &format!("sort:{} ", name);
+            }
+            if self.sort != Sort::Position {
+                title += &format!("sort:{} ", self.sort.name());
+            }
+            if let Some(blocker) = &self.blocker {
+                title += &format!("blocker: {} ", blocker.subject);
+            }
+        }
+        let mut block = Block::new()
             .borders(Borders::ALL)
-            .title(" todo ");
+            .title(title);
+        if let Some(message) = &self.message {
+            block = block.title_bottom(Line::from(format!(" {} ", message).red()));
+        }
         Paragraph::new(lines).block(block).render(area, buf);
     }
 }
@@ -342,17 +1323,49 @@ impl Widget for &Todo<'_> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::{TimeZone, Utc};
+    use crate::db::Priority;
+    use crate::db::Db;
+    use crate::store::MemoryStore;
+    use uuid::Uuid;
+
+    fn todo_with(subjects: &[&str]) -> Todo<'static> {
+        let mut todo = Todo::new(Box::new(MemoryStore::new()));
+        for subject in subjects {
+            todo.add_task(Content {
+                subject: subject.to_string(),
+                body: String::new(),
+                tags: vec![],
+            });
+        }
+        todo
+    }
 
     #[test]
     fn test_render() {
-        let mut todo = Todo::new();
+        let mut todo = Todo::new(Box::new(MemoryStore::new()));
         todo.tasks = vec![Task {
             id: 1,
+            uuid: Uuid::nil(),
             done: false,
             subject: String::from("test_subject"),
             body: String::from("test_body"),
-            due: String::from("2025/01/01"),
-            created: String::from("2024-12-29 14:00:00"),
+            due: NaiveDate::from_ymd_opt(2025, 1, 1),
+            created: Utc.with_ymd_and_hms(2024, 12, 29, 14, 0, 0).unwrap(),
+            updated_at: Utc.with_ymd_and_hms(2024, 12, 29, 14, 0, 0).unwrap(),
+            completed_at: None,
+            deleted_at: None,
+            archived_at: None,
+            position: 0,
+            tags: vec![],
+            fields: vec![],
+            project_id: DEFAULT_PROJECT,
+            parent_id: None,
+            blockers: vec![],
+            blocked: false,
+            priority: Priority::None,
+            recurrence: None,
+            version: 0,
         }];
         let mut buf = Buffer::empty(Rect::new(0, 0, 50, 4));
         todo.render(buf.area, &mut buf);
@@ -361,7 +1374,7 @@ mod tests {
             vec!["┌ todo ──────────────────────────────────────────┐".into()],
             vec![
                 "│".into(),
-                 "2025/01/01 test_subject                         ".white().on_red(),
+                 "2025/01/01     test_subject                     ".white().on_red(),
                 "│".into(),
             ],
             vec!["│                                                │".into()],
@@ -374,8 +1387,488 @@ mod tests {
 
     #[test]
     fn test_key_event() {
-        let mut todo = Todo::new();
+        let mut todo = Todo::new(Box::new(MemoryStore::new()));
         todo.handle_key_press_event(KeyCode::F(1).into());
         assert!(todo.exit);
     }
+
+    #[test]
+    fn test_toggle_trash() {
+        let mut todo = Todo::new(Box::new(MemoryStore::new()));
+        todo.handle_key_press_event(KeyCode::Char('T').into());
+        assert!(todo.view == View::Trash);
+        assert!(todo.tasks.iter().all(|task| task.deleted_at.is_some()));
+
+        todo.handle_key_press_event(KeyCode::Char('T').into());
+        assert!(todo.view == View::Tasks);
+        assert!(todo.tasks.iter().all(|task| task.deleted_at.is_none()));
+    }
+
+    #[test]
+    fn test_archive() {
+        let mut todo = todo_with(&["alpha", "beta", "gamma"]);
+        todo.handle_key_press_event(KeyCode::Char('x').into());
+        assert_eq!(todo.message.as_deref(), Some("no completed tasks to archive"));
+
+        for i in [0, 2] {
+            todo.current = i;
+            todo.done_current();
+        }
+        todo.handle_key_press_event(KeyCode::Char('x').into());
+        assert_eq!(todo.message.as_deref(), Some("archived 2 completed tasks"));
+        let subjects: Vec<_> = todo.tasks.iter().map(|task| task.subject.as_str()).collect();
+        assert_eq!(subjects, ["beta"]);
+
+        todo.handle_key_press_event(KeyCode::Char('X').into());
+        assert!(todo.view == View::Archive);
+        let subjects: Vec<_> = todo.tasks.iter().map(|task| task.subject.as_str()).collect();
+        assert_eq!(subjects, ["gamma", "alpha"]);
+        todo.handle_key_press_event(KeyCode::Char('r').into());
+        assert_eq!(todo.tasks.len(), 1);
+
+        todo.handle_key_press_event(KeyCode::Char('X').into());
+        assert!(todo.view == View::Tasks);
+        assert_eq!(todo.tasks.len(), 2);
+        todo.handle_key_press_event(KeyCode::Char('u').into());
+        todo.handle_key_press_event(KeyCode::Char('u').into());
+        assert_eq!(todo.tasks.len(), 3);
+    }
+
+    #[test]
+    fn test_render_message() {
+        let mut todo = Todo::new(Box::new(MemoryStore::new()));
+        todo.tasks = vec![];
+        todo.message = Some(String::from("database is locked"));
+        let mut buf = Buffer::empty(Rect::new(0, 0, 30, 3));
+        todo.render(buf.area, &mut buf);
+
+        let expected = Buffer::with_lines(vec![
+            vec!["┌ todo ──────────────────────┐".into()],
+            vec!["│                            │".into()],
+            vec![
+                "└".into(),
+                " database is locked ".red(),
+                "────────┘".into(),
+            ],
+        ]);
+
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn test_undo_redo_key_event() {
+        let mut todo = todo_with(&["test_subject"]);
+        todo.handle_key_press_event(KeyCode::Char('d').into());
+        assert!(todo.tasks[0].done);
+
+        todo.handle_key_press_event(KeyCode::Char('u').into());
+        assert!(!todo.tasks[0].done);
+        todo.handle_key_press_event(KeyCode::Char('u').into());
+        assert!(todo.tasks.is_empty());
+        todo.handle_key_press_event(KeyCode::Char('u').into());
+        assert_eq!(todo.message.as_deref(), Some("nothing to undo"));
+
+        let redo = KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL);
+        todo.handle_key_press_event(redo);
+        todo.handle_key_press_event(redo);
+        assert!(todo.tasks[0].done);
+    }
+
+    #[test]
+    fn test_search() {
+        let mut todo = Todo::new(Box::new(MemoryStore::new()));
+        for (subject, body) in [("alpha", "first"), ("beta", "has alpha inside"), ("gamma", "")] {
+            todo.add_task(Content {
+                subject: String::from(subject),
+                body: String::from(body),
+                tags: vec![],
+            });
+        }
+
+        todo.handle_key_press_event(KeyCode::Char('/').into());
+        for c in "alp".chars() {
+            assert!(todo.prompt.handle_key_press_event(KeyCode::Char(c).into()));
+        }
+        todo.prompt.handle_key_press_event(KeyCode::Enter.into());
+        let input = todo.prompt.get_input().unwrap();
+        todo.search(input);
+        let subjects: Vec<_> = todo.tasks.iter().map(|task| task.subject.as_str()).collect();
+        assert_eq!(subjects, ["alpha", "beta"]);
+
+        let mut buf = Buffer::empty(Rect::new(0, 0, 40, 4));
+        todo.render(buf.area, &mut buf);
+        assert_eq!(
+            buf.content()[..40].iter().map(|cell| cell.symbol()).collect::<String>(),
+            "┌ todo /alp ───────────────────────────┐",
+        );
+        assert_eq!(
+            buf.content()[80..120].iter().map(|cell| cell.symbol()).collect::<String>(),
+            "│               beta has alpha inside  │",
+        );
+
+        todo.handle_key_press_event(KeyCode::Esc.into());
+        assert_eq!(todo.tasks.len(), 3);
+    }
+
+    #[test]
+    fn test_tags() {
+        let mut todo = Todo::new(Box::new(MemoryStore::new()));
+        for (subject, tags) in [("alpha", vec!["work"]), ("beta", vec![]), ("gamma", vec!["home", "work"])] {
+            todo.add_task(Content {
+                subject: String::from(subject),
+                body: String::new(),
+                tags: tags.into_iter().map(String::from).collect(),
+            });
+        }
+        todo.current = 1;
+
+        let mut buf = Buffer::empty(Rect::new(0, 0, 36, 5));
+        todo.render(buf.area, &mut buf);
+        let expected = Buffer::with_lines(vec![
+            Line::from("┌ todo ────────────────────────────┐"),
+            Line::from(vec![
+                "│               alpha ".into(),
+                " work ".black().bg(tag_color("work")),
+                "       │".into(),
+            ]),
+            Line::from(vec![
+                "│".into(),
+                "               beta               ".white().on_red(),
+                "│".into(),
+            ]),
+            Line::from(vec![
+                "│               gamma ".into(),
+                " home ".black().bg(tag_color("home")),
+                " ".into(),
+                " work ".black().bg(tag_color("work")),
+                "│".into(),
+            ]),
+            Line::from("└──────────────────────────────────┘"),
+        ]);
+        assert_eq!(buf, expected);
+
+        todo.filter_tags(String::from("#work"));
+        let subjects: Vec<_> = todo.tasks.iter().map(|task| task.subject.as_str()).collect();
+        assert_eq!(subjects, ["alpha", "gamma"]);
+        todo.filter_tags(String::from("work home"));
+        assert_eq!(todo.tasks.len(), 1);
+        todo.handle_key_press_event(KeyCode::Esc.into());
+        assert_eq!(todo.tasks.len(), 3);
+    }
+
+    #[test]
+    fn test_fields() {
+        let mut todo = todo_with(&["alpha", "beta", "gamma"]);
+
+        todo.current = 0;
+        todo.handle_key_press_event(KeyCode::Char('f').into());
+        for c in "points = 5".chars() {
+            assert!(todo.prompt.handle_key_press_event(KeyCode::Char(c).into()));
+        }
+        todo.prompt.handle_key_press_event(KeyCode::Enter.into());
+        let input = todo.prompt.get_input().unwrap();
+        todo.set_field(input);
+        assert_eq!(todo.tasks[0].field("points"), Some(&FieldValue::Number(5.0)));
+        todo.current = 1;
+        todo.set_field(String::from("points=2"));
+        todo.set_field(String::from("customer=ACME"));
+
+        todo.sort_by_field(String::from("points"));
+        let subjects: Vec<_> = todo.tasks.iter().map(|task| task.subject.as_str()).collect();
+        assert_eq!(subjects, ["beta", "alpha", "gamma"]);
+        todo.handle_key_press_event(KeyCode::Char('K').into());
+        assert_eq!(todo.message.as_deref(), Some("tasks are sorted by points"));
+        todo.sort_by_field(String::new());
+
+        todo.filter_fields(String::from("customer=ACME"));
+        let mut buf = Buffer::empty(Rect::new(0, 0, 36, 3));
+        todo.render(buf.area, &mut buf);
+        assert_eq!(
+            buf.content()[..36].iter().map(|cell| cell.symbol()).collect::<String>(),
+            "┌ todo customer=ACME ──────────────┐",
+        );
+        assert_eq!(todo.tasks.len(), 1);
+        todo.set_field(String::from("customer="));
+        assert!(todo.tasks.is_empty());
+        todo.handle_key_press_event(KeyCode::Esc.into());
+        assert_eq!(todo.tasks.len(), 3);
+
+        todo.undo();
+        assert_eq!(todo.tasks[1].field("customer"), Some(&FieldValue::parse("ACME")));
+
+        todo.current = 1;
+        todo.set_field(String::from("customer"));
+        assert_eq!(todo.message.as_deref(), Some("expected name=value, got customer"));
+        assert_eq!(todo.tasks[1].field("customer"), Some(&FieldValue::parse("ACME")));
+        todo.set_field(String::from("customer=ACME corp"));
+        todo.filter_fields(String::from("customer=\"ACME corp\"  points=2"));
+        assert_eq!(todo.tasks.len(), 1);
+        todo.handle_key_press_event(KeyCode::Char('F').into());
+        todo.prompt.handle_key_press_event(KeyCode::Enter.into());
+        assert_eq!(todo.prompt.get_input().as_deref(), Some("customer=\"ACME corp\" points=2"));
+        todo.filter_fields(String::from("points=2 customer"));
+        assert_eq!(todo.message.as_deref(), Some("expected name=value, got customer"));
+        todo.filter_fields(String::from("customer=\"ACME"));
+        assert_eq!(todo.message.as_deref(), Some("unclosed quote in customer=\"ACME"));
+        assert_eq!(todo.tasks.len(), 1);
+    }
+
+    #[test]
+    fn test_projects() {
+        let mut todo = todo_with(&["alpha"]);
+        todo.handle_project_action(ProjectAction::Create);
+        todo.prompt.handle_key_press_event(KeyCode::Char('w').into());
+        todo.prompt.handle_key_press_event(KeyCode::Enter.into());
+        let input = todo.prompt.get_input().unwrap();
+        todo.create_project(input);
+        assert_eq!(todo.project.name, "w");
+        assert!(todo.tasks.is_empty());
+
+        let mut buf = Buffer::empty(Rect::new(0, 0, 10, 3));
+        todo.render(buf.area, &mut buf);
+        assert_eq!(
+            buf.content()[..10].iter().map(|cell| cell.symbol()).collect::<String>(),
+            "┌ w ─────┐",
+        );
+
+        todo.switch_project(DEFAULT_PROJECT);
+        todo.move_current(todo.project.id + 1);
+        assert!(todo.tasks.is_empty());
+        todo.handle_key_press_event(KeyCode::Char('u').into());
+        assert_eq!(todo.tasks[0].subject, "alpha");
+    }
+
+    #[test]
+    fn test_subtasks() {
+        let mut todo = todo_with(&["alpha", "beta", "gamma", "delta"]);
+        todo.current = 1;
+        todo.handle_key_press_event(KeyCode::Char('>').into());
+        todo.current = 2;
+        todo.handle_key_press_event(KeyCode::Char('>').into());
+        todo.handle_key_press_event(KeyCode::Char('>').into());
+        todo.handle_key_press_event(KeyCode::Char('d').into());
+        assert_eq!(todo.depths, [0, 1, 2, 0]);
+
+        let mut buf = Buffer::empty(Rect::new(0, 0, 32, 6));
+        todo.render(buf.area, &mut buf);
+        let lines: Vec<String> = (1..5)
+            .map(|y| (1..31).map(|x| buf[(x, y)].symbol()).collect())
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "               ▾ alpha [1/2]  ",
+                "                 ▾ beta [1/1] ",
+                "                   gamma      ",
+                "               delta          ",
+            ]
+        );
+
+        todo.current = 0;
+        todo.handle_key_press_event(KeyCode::Char('h').into());
+        assert_eq!(todo.tasks.len(), 2);
+        todo.handle_key_press_event(KeyCode::Char('J').into());
+        let subjects: Vec<_> = todo.tasks.iter().map(|task| task.subject.as_str()).collect();
+        assert_eq!(subjects, ["delta", "alpha"]);
+        assert_eq!(todo.current, 1);
+        todo.handle_key_press_event(KeyCode::Char('l').into());
+        assert_eq!(todo.tasks.len(), 4);
+
+        todo.current = 2;
+        todo.handle_key_press_event(KeyCode::Char('<').into());
+        assert_eq!(todo.depths, [0, 0, 1, 0]);
+        assert_eq!(todo.tasks[todo.current].subject, "beta");
+        todo.handle_key_press_event(KeyCode::Char('u').into());
+        assert_eq!(todo.depths, [0, 0, 1, 2]);
+    }
+
+    #[test]
+    fn test_dependencies() {
+        let mut todo = todo_with(&["alpha", "beta"]);
+        todo.current = 0;
+        todo.handle_key_press_event(KeyCode::Char('b').into());
+        todo.handle_key_press_event(KeyCode::Char('j').into());
+        todo.handle_key_press_event(KeyCode::Char('b').into());
+        assert!(todo.tasks[1].blocked);
+
+        let mut buf = Buffer::empty(Rect::new(0, 0, 24, 4));
+        todo.render(buf.area, &mut buf);
+        let line: String = (1..23).map(|x| buf[(x, 2)].symbol()).collect();
+        assert_eq!(line, "               ⊘ beta ");
+
+        todo.current = 0;
+        todo.handle_key_press_event(KeyCode::Char('b').into());
+        todo.handle_key_press_event(KeyCode::Char('j').into());
+        todo.handle_key_press_event(KeyCode::Char('b').into());
+        todo.current = 1;
+        todo.handle_key_press_event(KeyCode::Char('b').into());
+        todo.current = 0;
+        todo.handle_key_press_event(KeyCode::Char('b').into());
+        assert!(todo.message.as_deref().unwrap().contains("depends on it"));
+
+        todo.handle_key_press_event(KeyCode::Char('d').into());
+        assert_eq!(todo.message.as_deref(), Some("unblocked: beta"));
+        assert!(!todo.tasks[1].blocked);
+        todo.handle_key_press_event(KeyCode::Char('u').into());
+        assert!(todo.tasks[1].blocked);
+        todo.current = 1;
+        todo.handle_key_press_event(KeyCode::Char('B').into());
+        assert!(todo.tasks[1].blockers.is_empty());
+    }
+
+    #[test]
+    fn test_priority() {
+        let mut todo = todo_with(&["alpha", "beta", "gamma"]);
+        todo.current = 2;
+        todo.handle_key_press_event(KeyCode::Char('+').into());
+        todo.handle_key_press_event(KeyCode::Char('+').into());
+        todo.current = 1;
+        todo.handle_key_press_event(KeyCode::Char('+').into());
+        todo.handle_key_press_event(KeyCode::Char('+').into());
+        todo.handle_key_press_event(KeyCode::Char('-').into());
+        assert_eq!(todo.tasks[1].priority, Priority::Low);
+
+        todo.handle_key_press_event(KeyCode::Char('o').into());
+        let subjects: Vec<_> = todo.tasks.iter().map(|task| task.subject.as_str()).collect();
+        assert_eq!(subjects, ["gamma", "beta", "alpha"]);
+
+        let mut buf = Buffer::empty(Rect::new(0, 0, 32, 3));
+        todo.render(buf.area, &mut buf);
+        let line: String = (0..32).map(|x| buf[(x, 0)].symbol()).collect();
+        assert_eq!(line, "┌ todo sort:priority ──────────┐");
+        let line: String = (1..31).map(|x| buf[(x, 1)].symbol()).collect();
+        assert_eq!(line, "           !   beta           ");
+
+        todo.handle_key_press_event(KeyCode::Char('J').into());
+        assert_eq!(todo.message.as_deref(), Some("tasks are sorted by priority"));
+    }
+
+    #[test]
+    fn test_add_selects_task() {
+        let mut todo = todo_with(&["alpha", "beta"]);
+        todo.store.insert_one(DEFAULT_PROJECT, "gamma", "").unwrap();
+        todo.add_task(Content {
+            subject: String::from("delta"),
+            body: String::new(),
+            tags: vec![],
+        });
+        assert_eq!(todo.tasks.len(), 4);
+        assert_eq!(todo.tasks[todo.current].subject, "delta");
+    }
+
+    #[test]
+    fn test_revisions() {
+        let mut todo = Todo::new(Box::new(MemoryStore::new()));
+        todo.handle_key_press_event(KeyCode::Char('H').into());
+        todo.add_task(Content {
+            subject: String::from("plan"),
+            body: String::from("keep this"),
+            tags: vec![],
+        });
+        todo.handle_key_press_event(KeyCode::Char('H').into());
+        assert_eq!(todo.message.as_deref(), Some("no earlier revisions"));
+
+        todo.update_current(Content {
+            subject: String::from("plan"),
+            body: String::from("overwritten"),
+            tags: vec![],
+        });
+        todo.handle_key_press_event(KeyCode::Char('H').into());
+        assert!(todo.revision_view.handle_key_press_event(KeyCode::Enter.into()));
+        let revision = todo.revision_view.get_revision().unwrap();
+        todo.revert_current(revision);
+        assert_eq!(todo.tasks[0].body, "keep this");
+        assert_eq!(todo.store.revisions(todo.tasks[0].id).unwrap().len(), 2);
+
+        todo.undo();
+        assert_eq!(todo.tasks[0].body, "overwritten");
+    }
+
+    #[test]
+    fn test_refresh() {
+        let path = std::env::temp_dir().join(format!("rustodo-refresh-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let other = Db::open(&path).unwrap();
+        let mut todo = Todo::new(Box::new(Db::open(&path).unwrap()));
+        for subject in ["alpha", "beta"] {
+            todo.add_task(Content {
+                subject: String::from(subject),
+                body: String::new(),
+                tags: vec![],
+            });
+        }
+        todo.current = 1;
+
+        let gamma = other.insert_one(DEFAULT_PROJECT, "gamma", "").unwrap().unwrap();
+        other.move_to(gamma.id, 0).unwrap();
+        let mut beta = other.get_one(todo.tasks[1].id).unwrap().unwrap();
+        beta.subject = String::from("beta edited");
+        other.update_one(&beta).unwrap();
+
+        todo.handle_key_press_event(KeyCode::Char('+').into());
+        assert_eq!(todo.message.as_deref(), Some("task 2 was changed by another writer"));
+        assert_eq!(todo.tasks[2].subject, "beta edited");
+
+        other.insert_one(DEFAULT_PROJECT, "delta", "").unwrap();
+        todo.refresh_if_changed();
+        let subjects: Vec<_> = todo.tasks.iter().map(|task| task.subject.as_str()).collect();
+        assert_eq!(subjects, ["gamma", "alpha", "beta edited", "delta"]);
+        assert_eq!(todo.tasks[todo.current].subject, "beta edited");
+
+        drop((todo, other));
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+
+    #[test]
+    fn test_recurrence() {
+        let mut todo = todo_with(&["invoice"]);
+        todo.set_recurrence(String::from("FREQ=MONTHLY;BYDAY=MO"));
+        assert_eq!(todo.message.as_deref(), Some("BYDAY is only supported with FREQ=WEEKLY"));
+        todo.set_recurrence(String::from("freq=monthly"));
+        todo.update_due(NaiveDate::from_ymd_opt(2020, 1, 31).unwrap());
+
+        let mut buf = Buffer::empty(Rect::new(0, 0, 36, 3));
+        todo.render(buf.area, &mut buf);
+        let line: String = (1..35).map(|x| buf[(x, 1)].symbol()).collect();
+        assert_eq!(line, "2020/01/31     invoice ↻          ");
+
+        todo.handle_key_press_event(KeyCode::Char('d').into());
+        assert_eq!(todo.tasks.len(), 2);
+        assert!(todo.tasks[0].done);
+        let next = todo.tasks[1].due.unwrap();
+        assert!(next > Local::now().date_naive());
+        assert!(todo.message.as_deref().unwrap().starts_with("next due "));
+
+        todo.handle_key_press_event(KeyCode::Char('u').into());
+        assert_eq!(todo.tasks.len(), 1);
+        assert!(!todo.tasks[0].done);
+        let redo = KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL);
+        todo.handle_key_press_event(redo);
+        assert_eq!(todo.tasks.len(), 2);
+    }
+
+    #[test]
+    fn test_timer() {
+        let mut todo = todo_with(&["alpha", "beta"]);
+        todo.current = 0;
+        todo.handle_key_press_event(KeyCode::Char('t').into());
+        assert_eq!(todo.timer.as_ref().unwrap().task_id, todo.tasks[0].id);
+        let bottom = |todo: &Todo| {
+            let mut buf = Buffer::empty(Rect::new(0, 0, 32, 3));
+            todo.preview.render(buf.area, &mut buf);
+            (0..32).map(|x| buf[(x, 2)].symbol()).collect::<String>()
+        };
+        assert!(bottom(&todo).starts_with("┴─── ● 0:00:0"));
+        assert!(bottom(&todo).ends_with(" total ┘"));
+
+        todo.handle_key_press_event(KeyCode::Char('j').into());
+        assert_eq!(bottom(&todo), "┴──────────────────────────────┘");
+        todo.handle_key_press_event(KeyCode::Char('t').into());
+        assert_eq!(todo.timer.as_ref().unwrap().task_id, todo.tasks[1].id);
+        todo.handle_key_press_event(KeyCode::Char('t').into());
+        assert!(todo.timer.is_none());
+        assert!(todo.store.running_timer().unwrap().is_none());
+    }
 }